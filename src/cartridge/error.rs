@@ -0,0 +1,64 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(io::Error),
+    // The image is smaller than the header or the ROM size it declares
+    Truncated { expected: usize, actual: usize },
+    HeaderChecksum { expected: u8, actual: u8 },
+    GlobalChecksum { expected: u16, actual: u16 },
+    UnsupportedCartridgeType(u8),
+    InvalidRomSize(u8),
+    InvalidRamSize(u8),
+    // A .sav file that doesn't match the size of the cartridge RAM (plus RTC data)
+    InvalidSaveData { expected: usize, actual: usize },
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CartridgeError::Io(err) => write!(f, "Failed to read cartridge: {}", err),
+            CartridgeError::Truncated { expected, actual } => write!(
+                f,
+                "Cartridge is truncated: expected {} bytes but found {}",
+                expected, actual
+            ),
+            CartridgeError::HeaderChecksum { expected, actual } => write!(
+                f,
+                "Header checksum mismatch: expected 0x{:02x} but computed 0x{:02x}",
+                expected, actual
+            ),
+            CartridgeError::GlobalChecksum { expected, actual } => write!(
+                f,
+                "Global checksum mismatch: expected 0x{:04x} but computed 0x{:04x}",
+                expected, actual
+            ),
+            CartridgeError::UnsupportedCartridgeType(byte) => {
+                write!(f, "Unsupported cartridge type: 0x{:02x}", byte)
+            }
+            CartridgeError::InvalidRomSize(byte) => write!(f, "Invalid ROM size: 0x{:02x}", byte),
+            CartridgeError::InvalidRamSize(byte) => write!(f, "Invalid RAM size: 0x{:02x}", byte),
+            CartridgeError::InvalidSaveData { expected, actual } => write!(
+                f,
+                "Invalid save data: expected {} bytes of RAM but found {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CartridgeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl std::convert::From<io::Error> for CartridgeError {
+    fn from(err: io::Error) -> Self {
+        CartridgeError::Io(err)
+    }
+}
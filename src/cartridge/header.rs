@@ -0,0 +1,309 @@
+use super::error::CartridgeError;
+
+pub const NINTENDO_LOGO_ADDRESS: usize = 0x0104;
+pub const TITLE_START: usize = 0x0134;
+pub const TITLE_END: usize = 0x0143;
+pub const NEW_LICENSEE_CODE_ADDRESS: usize = 0x0144;
+pub const CGB_FLAG_ADDRESS: usize = 0x0143;
+pub const SGB_FLAG_ADDRESS: usize = 0x0146;
+pub const CARTRIDGE_TYPE_ADDRESS: usize = 0x0147;
+pub const ROM_SIZE_ADDRESS: usize = 0x0148;
+pub const RAM_SIZE_ADDRESS: usize = 0x0149;
+pub const OLD_LICENSEE_CODE_ADDRESS: usize = 0x014B;
+pub const VERSION_ADDRESS: usize = 0x014C;
+pub const HEADER_CHECKSUM_ADDRESS: usize = 0x014D;
+pub const GLOBAL_CHECKSUM_ADDRESS: usize = 0x014E;
+
+// The header ends right before the entry point of the game at 0x0150
+pub const HEADER_END: usize = 0x0150;
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+// The logo every licensed cartridge has at 0x0104-0x0133
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+// An old licensee code of 0x33 means the new licensee code is used instead
+const USE_NEW_LICENSEE_CODE: u8 = 0x33;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mapper {
+    RomOnly,
+    MBC1,
+    MBC2,
+    MBC3,
+    MBC5,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CartridgeType {
+    pub mapper: Mapper,
+    pub ram: bool,
+    pub battery: bool,
+    pub timer: bool,
+    pub rumble: bool,
+}
+
+impl CartridgeType {
+    pub fn from_byte(byte: u8) -> Result<CartridgeType, CartridgeError> {
+        let (mapper, ram, battery, timer, rumble) = match byte {
+            0x00 => (Mapper::RomOnly, false, false, false, false),
+            0x01 => (Mapper::MBC1, false, false, false, false),
+            0x02 => (Mapper::MBC1, true, false, false, false),
+            0x03 => (Mapper::MBC1, true, true, false, false),
+            // MBC2 RAM is built into the controller so it never has the RAM bit
+            0x05 => (Mapper::MBC2, false, false, false, false),
+            0x06 => (Mapper::MBC2, false, true, false, false),
+            0x08 => (Mapper::RomOnly, true, false, false, false),
+            0x09 => (Mapper::RomOnly, true, true, false, false),
+            0x0F => (Mapper::MBC3, false, true, true, false),
+            0x10 => (Mapper::MBC3, true, true, true, false),
+            0x11 => (Mapper::MBC3, false, false, false, false),
+            0x12 => (Mapper::MBC3, true, false, false, false),
+            0x13 => (Mapper::MBC3, true, true, false, false),
+            0x19 => (Mapper::MBC5, false, false, false, false),
+            0x1A => (Mapper::MBC5, true, false, false, false),
+            0x1B => (Mapper::MBC5, true, true, false, false),
+            0x1C => (Mapper::MBC5, false, false, false, true),
+            0x1D => (Mapper::MBC5, true, false, false, true),
+            0x1E => (Mapper::MBC5, true, true, false, true),
+            _ => return Err(CartridgeError::UnsupportedCartridgeType(byte)),
+        };
+
+        Ok(CartridgeType {
+            mapper,
+            ram,
+            battery,
+            timer,
+            rumble,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CgbSupport {
+    // A DMG game
+    None,
+    // Works on both DMG and CGB (0x80)
+    Compatible,
+    // Only works on CGB (0xC0)
+    Only,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Licensee {
+    // The single byte code at 0x014B
+    Old(u8),
+    // The 2 ASCII characters at 0x0144-0x0145
+    New([u8; 2]),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    pub title: String,
+    pub cgb: CgbSupport,
+    pub sgb: bool,
+    pub cartridge_type: CartridgeType,
+    // In bytes
+    pub rom_size: usize,
+    // In bytes
+    pub ram_size: usize,
+    pub licensee: Licensee,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl Header {
+    pub fn from_rom(rom: &[u8]) -> Result<Header, CartridgeError> {
+        if rom.len() < HEADER_END {
+            return Err(CartridgeError::Truncated {
+                expected: HEADER_END,
+                actual: rom.len(),
+            });
+        }
+
+        let cgb = match rom[CGB_FLAG_ADDRESS] {
+            0xC0 => CgbSupport::Only,
+            0x80 => CgbSupport::Compatible,
+            _ => CgbSupport::None,
+        };
+
+        // CGB games use the last byte of the title for the CGB flag
+        let title_end = match cgb {
+            CgbSupport::None => TITLE_END + 1,
+            _ => TITLE_END,
+        };
+        let title = &rom[TITLE_START..title_end];
+        let title_len = title.iter().position(|&b| b == 0).unwrap_or(title.len());
+        let title = String::from_utf8_lossy(&title[..title_len])
+            .trim_end()
+            .to_string();
+
+        let licensee = match rom[OLD_LICENSEE_CODE_ADDRESS] {
+            USE_NEW_LICENSEE_CODE => Licensee::New([
+                rom[NEW_LICENSEE_CODE_ADDRESS],
+                rom[NEW_LICENSEE_CODE_ADDRESS + 1],
+            ]),
+            code => Licensee::Old(code),
+        };
+
+        Ok(Header {
+            title,
+            cgb,
+            sgb: rom[SGB_FLAG_ADDRESS] == 0x03,
+            cartridge_type: CartridgeType::from_byte(rom[CARTRIDGE_TYPE_ADDRESS])?,
+            rom_size: rom_size_from_byte(rom[ROM_SIZE_ADDRESS])?,
+            ram_size: ram_size_from_byte(rom[RAM_SIZE_ADDRESS])?,
+            licensee,
+            version: rom[VERSION_ADDRESS],
+            header_checksum: rom[HEADER_CHECKSUM_ADDRESS],
+            global_checksum: (rom[GLOBAL_CHECKSUM_ADDRESS] as u16) << 8
+                | rom[GLOBAL_CHECKSUM_ADDRESS + 1] as u16,
+        })
+    }
+}
+
+fn rom_size_from_byte(byte: u8) -> Result<usize, CartridgeError> {
+    match byte {
+        // 32 KiB << n
+        0x00..=0x08 => Ok((2 * ROM_BANK_SIZE) << byte),
+        _ => Err(CartridgeError::InvalidRomSize(byte)),
+    }
+}
+
+fn ram_size_from_byte(byte: u8) -> Result<usize, CartridgeError> {
+    match byte {
+        0x00 => Ok(0),
+        // Unofficial 2 KiB size used by a few homebrew and early games
+        0x01 => Ok(RAM_BANK_SIZE / 4),
+        0x02 => Ok(RAM_BANK_SIZE),
+        0x03 => Ok(4 * RAM_BANK_SIZE),
+        0x04 => Ok(16 * RAM_BANK_SIZE),
+        0x05 => Ok(8 * RAM_BANK_SIZE),
+        _ => Err(CartridgeError::InvalidRamSize(byte)),
+    }
+}
+
+// The boot ROM refuses to start a game if this doesn't match the header
+pub fn header_checksum(rom: &[u8]) -> u8 {
+    rom[TITLE_START..=VERSION_ADDRESS]
+        .iter()
+        .fold(0u8, |sum, &b| sum.wrapping_sub(b).wrapping_sub(1))
+}
+
+// Sum of every byte in the ROM except the global checksum itself
+pub fn global_checksum(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|&(i, _)| i != GLOBAL_CHECKSUM_ADDRESS && i != GLOBAL_CHECKSUM_ADDRESS + 1)
+        .fold(0u16, |sum, (_, &b)| sum.wrapping_add(b as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom_with_header() -> Vec<u8> {
+        let mut rom = vec![0; 2 * ROM_BANK_SIZE];
+        rom[TITLE_START..TITLE_START + 6].copy_from_slice(b"TETRIS");
+        rom[CARTRIDGE_TYPE_ADDRESS] = 0x03;
+        rom[ROM_SIZE_ADDRESS] = 0x02;
+        rom[RAM_SIZE_ADDRESS] = 0x03;
+        rom[OLD_LICENSEE_CODE_ADDRESS] = 0x01;
+        rom[VERSION_ADDRESS] = 0x01;
+        rom[GLOBAL_CHECKSUM_ADDRESS] = 0x12;
+        rom[GLOBAL_CHECKSUM_ADDRESS + 1] = 0x34;
+        rom
+    }
+
+    #[test]
+    fn from_rom() {
+        let header = Header::from_rom(&rom_with_header()).unwrap();
+
+        assert_eq!(header.title, "TETRIS");
+        assert_eq!(header.cgb, CgbSupport::None);
+        assert!(!header.sgb);
+        assert_eq!(
+            header.cartridge_type,
+            CartridgeType {
+                mapper: Mapper::MBC1,
+                ram: true,
+                battery: true,
+                timer: false,
+                rumble: false,
+            }
+        );
+        assert_eq!(header.rom_size, 128 * 1024);
+        assert_eq!(header.ram_size, 32 * 1024);
+        assert_eq!(header.licensee, Licensee::Old(0x01));
+        assert_eq!(header.version, 0x01);
+        assert_eq!(header.global_checksum, 0x1234);
+    }
+
+    #[test]
+    fn from_rom_cgb_and_new_licensee() {
+        let mut rom = rom_with_header();
+        rom[TITLE_START..=TITLE_END].copy_from_slice(b"POKEMON CRYSTAL\xC0");
+        rom[SGB_FLAG_ADDRESS] = 0x03;
+        rom[OLD_LICENSEE_CODE_ADDRESS] = 0x33;
+        rom[NEW_LICENSEE_CODE_ADDRESS] = b'0';
+        rom[NEW_LICENSEE_CODE_ADDRESS + 1] = b'1';
+
+        let header = Header::from_rom(&rom).unwrap();
+        assert_eq!(header.title, "POKEMON CRYSTAL");
+        assert_eq!(header.cgb, CgbSupport::Only);
+        assert!(header.sgb);
+        assert_eq!(header.licensee, Licensee::New(*b"01"));
+    }
+
+    #[test]
+    fn from_rom_errors() {
+        assert!(matches!(
+            Header::from_rom(&[0; 0x100]),
+            Err(CartridgeError::Truncated {
+                expected: 0x150,
+                actual: 0x100
+            })
+        ));
+
+        let mut rom = rom_with_header();
+        rom[CARTRIDGE_TYPE_ADDRESS] = 0xFC;
+        assert!(matches!(
+            Header::from_rom(&rom),
+            Err(CartridgeError::UnsupportedCartridgeType(0xFC))
+        ));
+
+        let mut rom = rom_with_header();
+        rom[ROM_SIZE_ADDRESS] = 0x52;
+        assert!(matches!(
+            Header::from_rom(&rom),
+            Err(CartridgeError::InvalidRomSize(0x52))
+        ));
+
+        let mut rom = rom_with_header();
+        rom[RAM_SIZE_ADDRESS] = 0x06;
+        assert!(matches!(
+            Header::from_rom(&rom),
+            Err(CartridgeError::InvalidRamSize(0x06))
+        ));
+    }
+
+    #[test]
+    fn checksums() {
+        let mut rom = vec![0; HEADER_END];
+        assert_eq!(header_checksum(&rom), 0xE7);
+
+        rom[TITLE_START] = 0x01;
+        assert_eq!(header_checksum(&rom), 0xE6);
+
+        // The global checksum bytes themselves are skipped
+        rom[GLOBAL_CHECKSUM_ADDRESS] = 0xFF;
+        rom[0x0000] = 0x02;
+        assert_eq!(global_checksum(&rom), 0x0003);
+    }
+}
@@ -0,0 +1,94 @@
+use super::header::{RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+// Called with true when a rumble cartridge turns its motor on and false when it turns it off
+pub type RumbleCallback = Box<dyn FnMut(bool)>;
+
+// Maps the 0x0000-0x7FFF and 0xA000-0xBFFF windows onto the cartridge ROM and RAM.
+// Addresses are relative to the start of each window.
+pub trait MemoryBankController {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8;
+    // Writes to ROM set the controller's registers
+    fn write_rom(&mut self, address: u16, byte: u8);
+    fn read_ram(&self, ram: &[u8], address: u16) -> u8;
+    fn write_ram(&mut self, ram: &mut [u8], address: u16, byte: u8);
+
+    // The real time clock state in the format appended to .sav files
+    fn rtc_data(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    // Returns false if the controller has no real time clock or the data is invalid
+    fn load_rtc_data(&mut self, _data: &[u8]) -> bool {
+        false
+    }
+
+    fn set_rumble_callback(&mut self, _callback: RumbleCallback) {}
+}
+
+// Reads from a 16 KiB ROM bank, wrapping the bank number to the size of the ROM
+pub fn read_rom_bank(rom: &[u8], bank: usize, address: u16) -> u8 {
+    let banks = (rom.len() / ROM_BANK_SIZE).max(1);
+    let offset = (bank % banks) * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1));
+    rom.get(offset).copied().unwrap_or(0xFF)
+}
+
+// Index into RAM for an 8 KiB bank, wrapping the bank number to the size of the RAM.
+// Returns None when the cartridge has no RAM at that address.
+pub fn ram_bank_index(ram: &[u8], bank: usize, address: u16) -> Option<usize> {
+    if ram.is_empty() {
+        return None;
+    }
+    let banks = (ram.len() / RAM_BANK_SIZE).max(1);
+    let index = (bank % banks) * RAM_BANK_SIZE + (address as usize & (RAM_BANK_SIZE - 1));
+    if index < ram.len() {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+// A cartridge without a controller, ROM is mapped directly and RAM is always enabled
+pub struct RomOnly;
+
+impl MemoryBankController for RomOnly {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        rom.get(address as usize).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, _address: u16, _byte: u8) {}
+
+    fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        // Reading RAM that doesn't exist returns open bus
+        ram_bank_index(ram, 0, address).map_or(0xFF, |i| ram[i])
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], address: u16, byte: u8) {
+        if let Some(i) = ram_bank_index(ram, 0, address) {
+            ram[i] = byte;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_rom_bank_wraps() {
+        let mut rom = vec![0; 4 * ROM_BANK_SIZE];
+        rom[ROM_BANK_SIZE + 0x10] = 0x42;
+
+        assert_eq!(read_rom_bank(&rom, 1, 0x4010), 0x42);
+        assert_eq!(read_rom_bank(&rom, 5, 0x0010), 0x42);
+    }
+
+    #[test]
+    fn ram_bank_index_wraps() {
+        let ram = vec![0; 2 * RAM_BANK_SIZE];
+
+        assert_eq!(ram_bank_index(&ram, 1, 0x0001), Some(0x2001));
+        assert_eq!(ram_bank_index(&ram, 3, 0x0001), Some(0x2001));
+        assert_eq!(ram_bank_index(&[0; 0x800], 0, 0x0800), None);
+        assert_eq!(ram_bank_index(&[], 0, 0x0000), None);
+    }
+}
@@ -0,0 +1,219 @@
+use super::header::{NINTENDO_LOGO, NINTENDO_LOGO_ADDRESS, ROM_BANK_SIZE};
+use super::mbc::{ram_bank_index, read_rom_bank, MemoryBankController};
+
+// MBC1M multicarts are always 8 Mbit
+const MULTICART_ROM_SIZE: usize = 64 * ROM_BANK_SIZE;
+// Each game on a multicart is 16 banks and has its own header
+const MULTICART_GAME_BANKS: usize = 0x10;
+
+pub struct MBC1 {
+    ram_enabled: bool,
+    // BANK1, the 5 bit register at 0x2000-0x3FFF
+    rom_bank: u8,
+    // BANK2, the 2 bit register at 0x4000-0x5FFF used for
+    // the RAM bank or the upper bits of the ROM bank
+    upper_bank: u8,
+    // When set BANK2 also applies to 0x0000-0x3FFF and RAM
+    advanced_banking: bool,
+    // MBC1M wires BANK2 to bit 4 of the ROM bank instead of bit 5
+    multicart: bool,
+}
+
+impl MBC1 {
+    pub fn new(rom: &[u8]) -> Self {
+        MBC1 {
+            ram_enabled: false,
+            rom_bank: 1,
+            upper_bank: 0,
+            advanced_banking: false,
+            multicart: is_multicart(rom),
+        }
+    }
+
+    fn upper_bank_shift(&self) -> u8 {
+        if self.multicart {
+            4
+        } else {
+            5
+        }
+    }
+
+    fn low_rom_bank(&self) -> usize {
+        if self.advanced_banking {
+            (self.upper_bank << self.upper_bank_shift()) as usize
+        } else {
+            0
+        }
+    }
+
+    fn high_rom_bank(&self) -> usize {
+        let rom_bank = if self.multicart {
+            self.rom_bank & 0x0F
+        } else {
+            self.rom_bank
+        };
+        ((self.upper_bank << self.upper_bank_shift()) | rom_bank) as usize
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.advanced_banking {
+            self.upper_bank as usize
+        } else {
+            0
+        }
+    }
+}
+
+// Multicarts have the Nintendo logo of another game's header at bank 0x10
+fn is_multicart(rom: &[u8]) -> bool {
+    if rom.len() != MULTICART_ROM_SIZE {
+        return false;
+    }
+
+    let logo = MULTICART_GAME_BANKS * ROM_BANK_SIZE + NINTENDO_LOGO_ADDRESS;
+    rom[logo..logo + NINTENDO_LOGO.len()] == NINTENDO_LOGO[..]
+}
+
+impl MemoryBankController for MBC1 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => read_rom_bank(rom, self.low_rom_bank(), address),
+            _ => read_rom_bank(rom, self.high_rom_bank(), address),
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, byte: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = byte & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                // Bank 0 can't be selected here, it turns into bank 1.
+                // The check is on all 5 bits so 0x20, 0x40 and 0x60 map to 0x21, 0x41 and 0x61.
+                self.rom_bank = match byte & 0x1F {
+                    0 => 1,
+                    bank => bank,
+                };
+            }
+            0x4000..=0x5FFF => self.upper_bank = byte & 0x03,
+            _ => self.advanced_banking = byte & 0x01 == 0x01,
+        }
+    }
+
+    fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        ram_bank_index(ram, self.ram_bank(), address).map_or(0xFF, |i| ram[i])
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], address: u16, byte: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+        if let Some(i) = ram_bank_index(ram, self.ram_bank(), address) {
+            ram[i] = byte;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::header::RAM_BANK_SIZE;
+    use super::*;
+
+    // Every bank starts with its own bank number
+    fn numbered_rom(banks: usize) -> Vec<u8> {
+        let mut rom = vec![0; banks * ROM_BANK_SIZE];
+        for bank in 0..banks {
+            rom[bank * ROM_BANK_SIZE] = bank as u8;
+        }
+        rom
+    }
+
+    #[test]
+    fn rom_banking() {
+        let rom = numbered_rom(32);
+        let mut mbc = MBC1::new(&rom);
+
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 1);
+
+        mbc.write_rom(0x2000, 0x05);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 5);
+
+        // 0 selects bank 1
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 1);
+
+        // Banks past the end of the ROM wrap around
+        mbc.write_rom(0x3FFF, 0x1F);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x1F);
+        let small_rom = numbered_rom(8);
+        assert_eq!(mbc.read_rom(&small_rom, 0x4000), 0x07);
+    }
+
+    #[test]
+    fn large_rom_banking() {
+        let rom = numbered_rom(128);
+        let mut mbc = MBC1::new(&rom);
+
+        // BANK2 supplies bits 5-6 of the ROM bank
+        mbc.write_rom(0x4000, 0x02);
+        mbc.write_rom(0x2000, 0x03);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x43);
+        // and 0x40 maps to 0x41
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x41);
+
+        // In mode 0 0x0000-0x3FFF is always bank 0
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x00);
+        // In mode 1 it's remapped to 0x20, 0x40 or 0x60
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x40);
+    }
+
+    #[test]
+    fn ram_banking() {
+        let rom = numbered_rom(4);
+        let mut ram = vec![0; 4 * RAM_BANK_SIZE];
+        let mut mbc = MBC1::new(&rom);
+
+        // RAM is disabled by default
+        mbc.write_ram(&mut ram, 0x0000, 0x11);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0xFF);
+        assert_eq!(ram[0], 0x00);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_ram(&mut ram, 0x0000, 0x11);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0x11);
+
+        // BANK2 only selects the RAM bank in mode 1
+        mbc.write_rom(0x4000, 0x02);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0x11);
+        mbc.write_rom(0x6000, 0x01);
+        mbc.write_ram(&mut ram, 0x0000, 0x22);
+        assert_eq!(ram[2 * RAM_BANK_SIZE], 0x22);
+
+        // Only 0xA in the lower nibble enables RAM
+        mbc.write_rom(0x0000, 0x0B);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0xFF);
+    }
+
+    #[test]
+    fn multicart() {
+        let mut rom = numbered_rom(64);
+        assert!(!MBC1::new(&rom).multicart);
+
+        let logo = MULTICART_GAME_BANKS * ROM_BANK_SIZE + NINTENDO_LOGO_ADDRESS;
+        rom[logo..logo + NINTENDO_LOGO.len()].copy_from_slice(&NINTENDO_LOGO);
+        let mut mbc = MBC1::new(&rom);
+        assert!(mbc.multicart);
+
+        // BANK2 is bits 4-5 of the ROM bank and BANK1 only uses 4 bits
+        mbc.write_rom(0x4000, 0x01);
+        mbc.write_rom(0x2000, 0x12);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x12);
+
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x10);
+    }
+}
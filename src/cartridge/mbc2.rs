@@ -0,0 +1,108 @@
+use super::mbc::{read_rom_bank, MemoryBankController};
+
+// 512 half bytes of RAM are built into the controller
+pub const MBC2_RAM_SIZE: usize = 0x200;
+
+// Bit 8 of the address decides which register a write to 0x0000-0x3FFF goes to
+const ROM_BANK_SELECT_BIT: u16 = 0x0100;
+
+pub struct MBC2 {
+    ram_enabled: bool,
+    rom_bank: u8,
+}
+
+impl MBC2 {
+    pub fn new() -> Self {
+        MBC2 {
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+}
+
+impl MemoryBankController for MBC2 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => read_rom_bank(rom, 0, address),
+            _ => read_rom_bank(rom, self.rom_bank as usize, address),
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, byte: u8) {
+        match address {
+            0x0000..=0x3FFF if address & ROM_BANK_SELECT_BIT != 0 => {
+                self.rom_bank = match byte & 0x0F {
+                    0 => 1,
+                    bank => bank,
+                };
+            }
+            0x0000..=0x3FFF => self.ram_enabled = byte & 0x0F == 0x0A,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        // Only the lower 4 bits exist, the upper ones read as 1.
+        // The 512 bytes are repeated through all of 0xA000-0xBFFF.
+        0xF0 | ram[address as usize % MBC2_RAM_SIZE]
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], address: u16, byte: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+        ram[address as usize % MBC2_RAM_SIZE] = byte & 0x0F;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::header::ROM_BANK_SIZE;
+    use super::*;
+
+    #[test]
+    fn rom_banking() {
+        let mut rom = vec![0; 16 * ROM_BANK_SIZE];
+        rom[0x0F * ROM_BANK_SIZE] = 0x0F;
+        rom[ROM_BANK_SIZE] = 0x01;
+        let mut mbc = MBC2::new();
+
+        // Bit 8 clear is the RAM enable register
+        mbc.write_rom(0x2000, 0x0F);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+
+        mbc.write_rom(0x2100, 0x0F);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x0F);
+
+        mbc.write_rom(0x3FFF, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+    }
+
+    #[test]
+    fn ram() {
+        let mut ram = vec![0; MBC2_RAM_SIZE];
+        let mut mbc = MBC2::new();
+
+        mbc.write_ram(&mut ram, 0x0000, 0x0A);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0xFF);
+
+        // Bit 8 set is the ROM bank register
+        mbc.write_rom(0x0100, 0x0A);
+        mbc.write_ram(&mut ram, 0x0000, 0x0A);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0xFF);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_ram(&mut ram, 0x0000, 0x5A);
+        assert_eq!(ram[0], 0x0A);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0xFA);
+
+        // RAM is echoed every 512 bytes
+        assert_eq!(mbc.read_ram(&ram, 0x0200), 0xFA);
+        assert_eq!(mbc.read_ram(&ram, 0x1E00), 0xFA);
+        mbc.write_ram(&mut ram, 0x03FF, 0x03);
+        assert_eq!(ram[0x01FF], 0x03);
+    }
+}
@@ -0,0 +1,171 @@
+use super::mbc::{ram_bank_index, read_rom_bank, MemoryBankController};
+use super::rtc::{Clock, Rtc};
+
+pub struct MBC3 {
+    // Enables both RAM and the RTC registers
+    ram_enabled: bool,
+    rom_bank: u8,
+    // 0x00-0x03 selects a RAM bank and 0x08-0x0C an RTC register
+    ram_bank: u8,
+    // Latching happens when 0x00 then 0x01 is written to 0x6000-0x7FFF
+    last_latch_write: u8,
+    rtc: Option<Rtc>,
+}
+
+impl MBC3 {
+    pub fn new(clock: Option<Box<dyn Clock>>) -> Self {
+        MBC3 {
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            last_latch_write: 0xFF,
+            rtc: clock.map(Rtc::new),
+        }
+    }
+
+    fn rtc_selected(&self) -> bool {
+        (0x08..=0x0C).contains(&self.ram_bank)
+    }
+}
+
+impl MemoryBankController for MBC3 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => read_rom_bank(rom, 0, address),
+            _ => read_rom_bank(rom, self.rom_bank as usize, address),
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, byte: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = byte & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                // Unlike MBC1 only bank 0 itself maps to bank 1
+                self.rom_bank = match byte & 0x7F {
+                    0 => 1,
+                    bank => bank,
+                };
+            }
+            0x4000..=0x5FFF => self.ram_bank = byte & 0x0F,
+            _ => {
+                if self.last_latch_write == 0x00 && byte == 0x01 {
+                    if let Some(rtc) = self.rtc.as_mut() {
+                        rtc.latch();
+                    }
+                }
+                self.last_latch_write = byte;
+            }
+        }
+    }
+
+    fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        if self.rtc_selected() {
+            return self
+                .rtc
+                .as_ref()
+                .map_or(0xFF, |rtc| rtc.read(self.ram_bank));
+        }
+        ram_bank_index(ram, (self.ram_bank & 0x03) as usize, address).map_or(0xFF, |i| ram[i])
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], address: u16, byte: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+        if self.rtc_selected() {
+            if let Some(rtc) = self.rtc.as_mut() {
+                rtc.write(self.ram_bank, byte);
+            }
+            return;
+        }
+        if let Some(i) = ram_bank_index(ram, (self.ram_bank & 0x03) as usize, address) {
+            ram[i] = byte;
+        }
+    }
+
+    fn rtc_data(&self) -> Option<Vec<u8>> {
+        self.rtc.as_ref().map(Rtc::to_bytes)
+    }
+
+    fn load_rtc_data(&mut self, data: &[u8]) -> bool {
+        match self.rtc.as_mut() {
+            Some(rtc) => rtc.load_bytes(data),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::header::{RAM_BANK_SIZE, ROM_BANK_SIZE};
+    use super::super::rtc::tests::FakeClock;
+    use super::*;
+
+    #[test]
+    fn rom_banking() {
+        let mut rom = vec![0; 128 * ROM_BANK_SIZE];
+        rom[0x7F * ROM_BANK_SIZE] = 0x7F;
+        rom[0x20 * ROM_BANK_SIZE] = 0x20;
+        rom[ROM_BANK_SIZE] = 0x01;
+        let mut mbc = MBC3::new(None);
+
+        mbc.write_rom(0x2000, 0x7F);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x7F);
+
+        mbc.write_rom(0x2000, 0x20);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x20);
+
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+    }
+
+    #[test]
+    fn ram_banking() {
+        let mut ram = vec![0; 4 * RAM_BANK_SIZE];
+        let mut mbc = MBC3::new(None);
+
+        mbc.write_ram(&mut ram, 0x0000, 0x11);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0xFF);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x03);
+        mbc.write_ram(&mut ram, 0x0010, 0x33);
+        assert_eq!(ram[3 * RAM_BANK_SIZE + 0x10], 0x33);
+        assert_eq!(mbc.read_ram(&ram, 0x0010), 0x33);
+
+        // Without a timer the RTC registers read as open bus
+        mbc.write_rom(0x4000, 0x08);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0xFF);
+    }
+
+    #[test]
+    fn rtc() {
+        let clock = FakeClock::default();
+        let ram = vec![0; RAM_BANK_SIZE];
+        let mut mbc = MBC3::new(Some(Box::new(clock.clone())));
+
+        mbc.write_rom(0x0000, 0x0A);
+        clock.advance(2 * 3600 + 5);
+
+        // Nothing is visible until the registers are latched
+        mbc.write_rom(0x4000, 0x08);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0x00);
+
+        mbc.write_rom(0x6000, 0x00);
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 5);
+        mbc.write_rom(0x4000, 0x0A);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 2);
+
+        // Writing 0x01 again without a 0x00 first doesn't latch
+        clock.advance(1);
+        mbc.write_rom(0x6000, 0x01);
+        mbc.write_rom(0x4000, 0x08);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 5);
+
+        assert_eq!(mbc.rtc_data().unwrap()[0], 6);
+    }
+}
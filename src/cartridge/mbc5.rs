@@ -0,0 +1,147 @@
+use super::mbc::{ram_bank_index, read_rom_bank, MemoryBankController, RumbleCallback};
+
+// Rumble carts use bit 3 of the RAM bank register for the motor
+const RUMBLE_MOTOR_BIT: u8 = 0x08;
+
+pub struct MBC5 {
+    ram_enabled: bool,
+    // 9 bit ROM bank, bank 0 can be mapped to 0x4000-0x7FFF
+    rom_bank: u16,
+    ram_bank: u8,
+    rumble: bool,
+    motor_on: bool,
+    rumble_callback: Option<RumbleCallback>,
+}
+
+impl MBC5 {
+    pub fn new(rumble: bool) -> Self {
+        MBC5 {
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            rumble,
+            motor_on: false,
+            rumble_callback: None,
+        }
+    }
+
+    fn set_motor(&mut self, on: bool) {
+        if self.motor_on == on {
+            return;
+        }
+        self.motor_on = on;
+        if let Some(callback) = self.rumble_callback.as_mut() {
+            callback(on);
+        }
+    }
+}
+
+impl MemoryBankController for MBC5 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => read_rom_bank(rom, 0, address),
+            _ => read_rom_bank(rom, self.rom_bank as usize, address),
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, byte: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = byte & 0x0F == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | byte as u16,
+            0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | ((byte & 0x01) as u16) << 8,
+            0x4000..=0x5FFF => {
+                if self.rumble {
+                    self.set_motor(byte & RUMBLE_MOTOR_BIT != 0);
+                    self.ram_bank = byte & 0x07;
+                } else {
+                    self.ram_bank = byte & 0x0F;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        ram_bank_index(ram, self.ram_bank as usize, address).map_or(0xFF, |i| ram[i])
+    }
+
+    fn write_ram(&mut self, ram: &mut [u8], address: u16, byte: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+        if let Some(i) = ram_bank_index(ram, self.ram_bank as usize, address) {
+            ram[i] = byte;
+        }
+    }
+
+    fn set_rumble_callback(&mut self, callback: RumbleCallback) {
+        self.rumble_callback = Some(callback);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::header::{RAM_BANK_SIZE, ROM_BANK_SIZE};
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn rom_banking() {
+        let mut rom = vec![0; 512 * ROM_BANK_SIZE];
+        rom[0x1FF * ROM_BANK_SIZE] = 0xFF;
+        rom[0x100 * ROM_BANK_SIZE] = 0x10;
+        rom[0x01] = 0x01;
+        let mut mbc = MBC5::new(false);
+
+        mbc.write_rom(0x2000, 0xFF);
+        mbc.write_rom(0x3000, 0x01);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0xFF);
+
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x10);
+
+        // Bank 0 can be mapped to 0x4000-0x7FFF
+        mbc.write_rom(0x3000, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4001), 0x01);
+    }
+
+    #[test]
+    fn ram_banking() {
+        let mut ram = vec![0; 16 * RAM_BANK_SIZE];
+        let mut mbc = MBC5::new(false);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x0F);
+        mbc.write_ram(&mut ram, 0x0000, 0x42);
+        assert_eq!(ram[15 * RAM_BANK_SIZE], 0x42);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0x42);
+
+        mbc.write_rom(0x0000, 0x00);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0xFF);
+    }
+
+    #[test]
+    fn rumble() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut ram = vec![0; 8 * RAM_BANK_SIZE];
+        let mut mbc = MBC5::new(true);
+
+        let recorded = events.clone();
+        mbc.set_rumble_callback(Box::new(move |on| recorded.borrow_mut().push(on)));
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x0B);
+        // Writing the same state again doesn't send another event
+        mbc.write_rom(0x4000, 0x0B);
+        mbc.write_rom(0x4000, 0x03);
+        assert_eq!(*events.borrow(), vec![true, false]);
+
+        // The motor bit isn't part of the RAM bank
+        mbc.write_ram(&mut ram, 0x0000, 0x42);
+        assert_eq!(ram[3 * RAM_BANK_SIZE], 0x42);
+    }
+}
@@ -0,0 +1,479 @@
+mod error;
+mod header;
+mod mbc;
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+mod rtc;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub use error::CartridgeError;
+pub use header::{CartridgeType, CgbSupport, Header, Licensee, Mapper};
+pub use mbc::RumbleCallback;
+pub use rtc::{Clock, SystemClock};
+
+use header::{global_checksum, header_checksum, ROM_BANK_SIZE};
+use mbc::{MemoryBankController, RomOnly};
+use mbc1::MBC1;
+use mbc2::{MBC2, MBC2_RAM_SIZE};
+use mbc3::MBC3;
+use mbc5::MBC5;
+use rtc::{RTC_DATA_SIZE, RTC_DATA_SIZE_SHORT};
+
+pub struct Cartridge {
+    header: Header,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    mbc: Box<dyn MemoryBankController>,
+    // Checked on load but not enforced, since plenty of real ROMs get it wrong
+    global_checksum_valid: bool,
+    // Where battery backed RAM is flushed to
+    save_path: Option<PathBuf>,
+}
+
+impl Default for Cartridge {
+    // A blank 32 KiB cartridge without RAM, as if nothing was inserted
+    fn default() -> Self {
+        let rom = vec![0; 2 * ROM_BANK_SIZE];
+        let header = Header::from_rom(&rom).expect("a blank ROM has a valid header");
+        Cartridge {
+            global_checksum_valid: global_checksum(&rom) == header.global_checksum,
+            header,
+            rom,
+            ram: Vec::new(),
+            mbc: Box::new(RomOnly),
+            save_path: None,
+        }
+    }
+}
+
+impl Cartridge {
+    // Fails on a bad header checksum like the boot ROM does. The global checksum
+    // is only checked, real hardware ignores it and many ROMs get it wrong, so
+    // see global_checksum_valid or verify_global_checksum for the result.
+    pub fn from_bytes(rom: Vec<u8>) -> Result<Cartridge, CartridgeError> {
+        Self::from_bytes_with_clock(rom, Box::new(SystemClock))
+    }
+
+    // Same as from_bytes but the real time clock of MBC3 cartridges
+    // keeps time from the given clock instead of the system time
+    pub fn from_bytes_with_clock(
+        rom: Vec<u8>,
+        clock: Box<dyn Clock>,
+    ) -> Result<Cartridge, CartridgeError> {
+        let header = Header::from_rom(&rom)?;
+
+        let checksum = header_checksum(&rom);
+        if checksum != header.header_checksum {
+            return Err(CartridgeError::HeaderChecksum {
+                expected: header.header_checksum,
+                actual: checksum,
+            });
+        }
+
+        if rom.len() < header.rom_size {
+            return Err(CartridgeError::Truncated {
+                expected: header.rom_size,
+                actual: rom.len(),
+            });
+        }
+
+        let mbc: Box<dyn MemoryBankController> = match header.cartridge_type.mapper {
+            Mapper::RomOnly => Box::new(RomOnly),
+            Mapper::MBC1 => Box::new(MBC1::new(&rom)),
+            Mapper::MBC2 => Box::new(MBC2::new()),
+            Mapper::MBC3 => {
+                let clock = if header.cartridge_type.timer {
+                    Some(clock)
+                } else {
+                    None
+                };
+                Box::new(MBC3::new(clock))
+            }
+            Mapper::MBC5 => Box::new(MBC5::new(header.cartridge_type.rumble)),
+        };
+
+        // MBC2 has its RAM built in so the header says there is none
+        let ram = match header.cartridge_type.mapper {
+            Mapper::MBC2 => vec![0; MBC2_RAM_SIZE],
+            _ => vec![0; header.ram_size],
+        };
+
+        Ok(Cartridge {
+            global_checksum_valid: global_checksum(&rom) == header.global_checksum,
+            header,
+            rom,
+            ram,
+            mbc,
+            save_path: None,
+        })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Cartridge, CartridgeError> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    // Whether the global checksum matched the header when the ROM was loaded
+    pub fn global_checksum_valid(&self) -> bool {
+        self.global_checksum_valid
+    }
+
+    // The hardware never checks the global checksum so it isn't enforced on load
+    pub fn verify_global_checksum(&self) -> Result<(), CartridgeError> {
+        let checksum = global_checksum(&self.rom);
+        if checksum != self.header.global_checksum {
+            return Err(CartridgeError::GlobalChecksum {
+                expected: self.header.global_checksum,
+                actual: checksum,
+            });
+        }
+        Ok(())
+    }
+
+    // Reads from 0x0000-0x7FFF
+    pub fn read_rom(&self, address: u16) -> u8 {
+        self.mbc.read_rom(&self.rom, address)
+    }
+
+    // Writes to 0x0000-0x7FFF control the memory bank controller
+    pub fn write_rom(&mut self, address: u16, byte: u8) {
+        self.mbc.write_rom(address, byte);
+    }
+
+    // Reads from 0xA000-0xBFFF, address is relative to 0xA000
+    pub fn read_ram(&self, address: u16) -> u8 {
+        self.mbc.read_ram(&self.ram, address)
+    }
+
+    // Writes to 0xA000-0xBFFF, address is relative to 0xA000
+    pub fn write_ram(&mut self, address: u16, byte: u8) {
+        self.mbc.write_ram(&mut self.ram, address, byte);
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.header.cartridge_type.battery
+    }
+
+    // The contents of a .sav file: external RAM followed by the real time clock
+    // state if there is one. None if the cartridge doesn't keep anything on a battery.
+    pub fn save_data(&self) -> Option<Vec<u8>> {
+        if !self.has_battery() {
+            return None;
+        }
+
+        let mut data = self.ram.clone();
+        if let Some(rtc) = self.rtc_data() {
+            data.extend_from_slice(&rtc);
+        }
+        Some(data)
+    }
+
+    // Restores RAM (and the real time clock) from the contents of a .sav file
+    pub fn load_save_data(&mut self, data: &[u8]) -> Result<(), CartridgeError> {
+        let ram_size = self.ram.len();
+        let rtc_size = data.len().saturating_sub(ram_size);
+        let valid_size = match rtc_size {
+            0 => true,
+            RTC_DATA_SIZE | RTC_DATA_SIZE_SHORT => self.mbc.load_rtc_data(&data[ram_size..]),
+            _ => false,
+        };
+        if data.len() < ram_size || !valid_size {
+            return Err(CartridgeError::InvalidSaveData {
+                expected: ram_size,
+                actual: data.len(),
+            });
+        }
+
+        self.ram.copy_from_slice(&data[..ram_size]);
+        Ok(())
+    }
+
+    // Loads battery backed RAM from the .sav file at path if it exists and
+    // remembers the path so flush_save and dropping the cartridge write back to it
+    pub fn attach_save_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), CartridgeError> {
+        let path = path.as_ref().to_path_buf();
+        match fs::read(&path) {
+            Ok(data) => self.load_save_data(&data)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        self.save_path = Some(path);
+        Ok(())
+    }
+
+    // Writes battery backed RAM to the attached .sav file
+    pub fn flush_save(&self) -> Result<(), CartridgeError> {
+        if let (Some(path), Some(data)) = (self.save_path.as_ref(), self.save_data()) {
+            fs::write(path, data)?;
+        }
+        Ok(())
+    }
+
+    // The real time clock state to persist alongside save RAM, if the cartridge has one
+    pub fn rtc_data(&self) -> Option<Vec<u8>> {
+        self.mbc.rtc_data()
+    }
+
+    pub fn load_rtc_data(&mut self, data: &[u8]) -> bool {
+        self.mbc.load_rtc_data(data)
+    }
+
+    // Rumble cartridges call this whenever the motor turns on or off
+    pub fn set_rumble_callback(&mut self, callback: RumbleCallback) {
+        self.mbc.set_rumble_callback(callback);
+    }
+
+    // Lets tests place program bytes in ROM
+    #[cfg(test)]
+    pub fn write_rom_byte(&mut self, address: u16, byte: u8) {
+        self.rom[address as usize] = byte;
+    }
+}
+
+impl Drop for Cartridge {
+    // Don't lose progress when the emulator shuts down
+    fn drop(&mut self) {
+        // There is nobody left to report an error to
+        let _ = self.flush_save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::header::*;
+    use super::*;
+
+    // Builds a ROM of the given type and size with valid checksums
+    pub fn test_rom(cartridge_type: u8, rom_size: u8, ram_size: u8) -> Vec<u8> {
+        let mut rom = vec![0; (2 * ROM_BANK_SIZE) << rom_size];
+        rom[TITLE_START..TITLE_START + 4].copy_from_slice(b"TEST");
+        rom[CARTRIDGE_TYPE_ADDRESS] = cartridge_type;
+        rom[ROM_SIZE_ADDRESS] = rom_size;
+        rom[RAM_SIZE_ADDRESS] = ram_size;
+        rom[HEADER_CHECKSUM_ADDRESS] = header_checksum(&rom);
+
+        let checksum = global_checksum(&rom);
+        rom[GLOBAL_CHECKSUM_ADDRESS] = (checksum >> 8) as u8;
+        rom[GLOBAL_CHECKSUM_ADDRESS + 1] = checksum as u8;
+        rom
+    }
+
+    #[test]
+    fn from_bytes() {
+        let cartridge = Cartridge::from_bytes(test_rom(0x08, 0x00, 0x02)).unwrap();
+
+        assert_eq!(cartridge.header().title, "TEST");
+        assert_eq!(cartridge.header().cartridge_type.mapper, Mapper::RomOnly);
+        assert!(cartridge.verify_global_checksum().is_ok());
+        assert!(cartridge.global_checksum_valid());
+        assert_eq!(cartridge.ram.len(), 0x2000);
+    }
+
+    #[test]
+    fn from_bytes_bad_header_checksum() {
+        let mut rom = test_rom(0x00, 0x00, 0x00);
+        rom[HEADER_CHECKSUM_ADDRESS] ^= 0xFF;
+
+        assert!(matches!(
+            Cartridge::from_bytes(rom),
+            Err(CartridgeError::HeaderChecksum { .. })
+        ));
+    }
+
+    #[test]
+    fn from_bytes_truncated() {
+        let mut rom = test_rom(0x00, 0x00, 0x00);
+        rom.truncate(0x4000);
+
+        assert!(matches!(
+            Cartridge::from_bytes(rom),
+            Err(CartridgeError::Truncated {
+                expected: 0x8000,
+                actual: 0x4000
+            })
+        ));
+    }
+
+    #[test]
+    fn verify_global_checksum() {
+        let mut rom = test_rom(0x00, 0x00, 0x00);
+        rom[0x7FFF] = 0x01;
+        // A bad global checksum still loads
+        let cartridge = Cartridge::from_bytes(rom).unwrap();
+
+        assert!(!cartridge.global_checksum_valid());
+        assert!(matches!(
+            cartridge.verify_global_checksum(),
+            Err(CartridgeError::GlobalChecksum { .. })
+        ));
+    }
+
+    #[test]
+    fn from_file() {
+        let path = std::env::temp_dir().join("gameboy-emu-rs-from-file.gb");
+        fs::write(&path, test_rom(0x00, 0x00, 0x00)).unwrap();
+
+        let cartridge = Cartridge::from_file(&path).unwrap();
+        assert_eq!(cartridge.header().title, "TEST");
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            Cartridge::from_file(&path),
+            Err(CartridgeError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn mbc1() {
+        let mut rom = test_rom(0x03, 0x02, 0x03);
+        rom[3 * ROM_BANK_SIZE] = 0x33;
+        let mut cartridge = Cartridge::from_bytes(rom).unwrap();
+
+        cartridge.write_rom(0x2000, 0x03);
+        assert_eq!(cartridge.read_rom(0x4000), 0x33);
+
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0x0000, 0x42);
+        assert_eq!(cartridge.read_ram(0x0000), 0x42);
+    }
+
+    #[test]
+    fn mbc3_rtc() {
+        let clock = rtc::tests::FakeClock::default();
+        let rom = test_rom(0x10, 0x01, 0x03);
+        let cartridge = Cartridge::from_bytes_with_clock(rom, Box::new(clock.clone())).unwrap();
+
+        clock.advance(59);
+        let data = cartridge.rtc_data().unwrap();
+        assert_eq!(data[0], 59);
+
+        let mut restored =
+            Cartridge::from_bytes_with_clock(test_rom(0x10, 0x01, 0x03), Box::new(clock.clone()))
+                .unwrap();
+        assert!(restored.load_rtc_data(&data));
+        clock.advance(1);
+        assert_eq!(restored.rtc_data().unwrap()[4], 1);
+
+        // MBC3 without the timer
+        let cartridge = Cartridge::from_bytes(test_rom(0x13, 0x01, 0x03)).unwrap();
+        assert!(cartridge.rtc_data().is_none());
+    }
+
+    #[test]
+    fn mbc5_rumble() {
+        let motor = std::rc::Rc::new(std::cell::Cell::new(false));
+        let mut cartridge = Cartridge::from_bytes(test_rom(0x1E, 0x03, 0x03)).unwrap();
+
+        let state = motor.clone();
+        cartridge.set_rumble_callback(Box::new(move |on| state.set(on)));
+        cartridge.write_rom(0x4000, 0x08);
+        assert!(motor.get());
+    }
+
+    #[test]
+    fn mbc2() {
+        let mut cartridge = Cartridge::from_bytes(test_rom(0x06, 0x01, 0x00)).unwrap();
+
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0x01FF, 0x0C);
+        assert_eq!(cartridge.read_ram(0x01FF), 0xFC);
+        assert_eq!(cartridge.ram.len(), 0x200);
+    }
+
+    #[test]
+    fn save_data() {
+        let mut cartridge = Cartridge::from_bytes(test_rom(0x03, 0x01, 0x02)).unwrap();
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0x0001, 0x42);
+
+        let data = cartridge.save_data().unwrap();
+        assert_eq!(data.len(), 0x2000);
+        assert_eq!(data[1], 0x42);
+
+        let mut restored = Cartridge::from_bytes(test_rom(0x03, 0x01, 0x02)).unwrap();
+        restored.load_save_data(&data).unwrap();
+        restored.write_rom(0x0000, 0x0A);
+        assert_eq!(restored.read_ram(0x0001), 0x42);
+
+        assert!(matches!(
+            restored.load_save_data(&data[..0x1000]),
+            Err(CartridgeError::InvalidSaveData {
+                expected: 0x2000,
+                actual: 0x1000
+            })
+        ));
+
+        // Without a battery there is nothing to save
+        let cartridge = Cartridge::from_bytes(test_rom(0x02, 0x01, 0x02)).unwrap();
+        assert!(cartridge.save_data().is_none());
+    }
+
+    #[test]
+    fn save_data_with_rtc() {
+        let clock = rtc::tests::FakeClock::default();
+        let rom = test_rom(0x10, 0x01, 0x02);
+        let mut cartridge = Cartridge::from_bytes_with_clock(rom, Box::new(clock.clone())).unwrap();
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0x0000, 0x42);
+        clock.advance(30);
+
+        let data = cartridge.save_data().unwrap();
+        assert_eq!(data.len(), 0x2000 + RTC_DATA_SIZE);
+        assert_eq!(data[0x2000], 30);
+
+        let rom = test_rom(0x10, 0x01, 0x02);
+        let mut restored = Cartridge::from_bytes_with_clock(rom, Box::new(clock.clone())).unwrap();
+        restored.load_save_data(&data).unwrap();
+        assert_eq!(restored.save_data().unwrap(), data);
+
+        // RAM only saves from emulators that don't write the RTC are fine too
+        restored.load_save_data(&data[..0x2000]).unwrap();
+    }
+
+    #[test]
+    fn attach_save_file() {
+        let path = std::env::temp_dir().join("gameboy-emu-rs-attach-save-file.sav");
+        let _ = fs::remove_file(&path);
+
+        {
+            let mut cartridge = Cartridge::from_bytes(test_rom(0x1B, 0x01, 0x02)).unwrap();
+            // A missing save file is a new game
+            cartridge.attach_save_file(&path).unwrap();
+            cartridge.write_rom(0x0000, 0x0A);
+            cartridge.write_ram(0x0100, 0x99);
+
+            cartridge.flush_save().unwrap();
+            assert_eq!(fs::read(&path).unwrap()[0x0100], 0x99);
+
+            cartridge.write_ram(0x0100, 0x98);
+            // dropping the cartridge flushes it again
+        }
+
+        let mut cartridge = Cartridge::from_bytes(test_rom(0x1B, 0x01, 0x02)).unwrap();
+        cartridge.attach_save_file(&path).unwrap();
+        cartridge.write_rom(0x0000, 0x0A);
+        assert_eq!(cartridge.read_ram(0x0100), 0x98);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn ram() {
+        let mut cartridge = Cartridge::from_bytes(test_rom(0x08, 0x00, 0x01)).unwrap();
+
+        cartridge.write_ram(0x07FF, 0x42);
+        assert_eq!(cartridge.read_ram(0x07FF), 0x42);
+
+        // Past the end of the 2 KiB of RAM
+        cartridge.write_ram(0x0800, 0x42);
+        assert_eq!(cartridge.read_ram(0x0800), 0xFF);
+    }
+}
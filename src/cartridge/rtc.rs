@@ -0,0 +1,301 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Size of the RTC block appended to .sav files by most emulators:
+// live and latched registers as 5 32 bit values each, then a 64 bit UNIX timestamp
+pub const RTC_DATA_SIZE: usize = 48;
+// Some emulators only write a 32 bit timestamp
+pub const RTC_DATA_SIZE_SHORT: usize = 44;
+
+const DAY_HIGH_BIT: u8 = 0x01;
+const HALT_BIT: u8 = 0x40;
+const DAY_CARRY_BIT: u8 = 0x80;
+
+// Where the RTC gets the current time from, in seconds
+pub trait Clock {
+    fn now(&self) -> u64;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct RtcRegisters {
+    pub seconds: u8,
+    pub minutes: u8,
+    pub hours: u8,
+    // 9 bit day counter
+    pub days: u16,
+    pub halted: bool,
+    // Set when the day counter overflows, only cleared by software
+    pub day_carry: bool,
+}
+
+impl RtcRegisters {
+    fn advance(&mut self, elapsed: u64) {
+        if self.halted {
+            return;
+        }
+
+        let seconds = self.seconds as u64 + elapsed;
+        self.seconds = (seconds % 60) as u8;
+        let minutes = self.minutes as u64 + seconds / 60;
+        self.minutes = (minutes % 60) as u8;
+        let hours = self.hours as u64 + minutes / 60;
+        self.hours = (hours % 24) as u8;
+        let days = self.days as u64 + hours / 24;
+        if days > 0x1FF {
+            self.day_carry = true;
+        }
+        self.days = (days % 0x200) as u16;
+    }
+
+    // RTC registers are selected by writing 0x08-0x0C to 0x4000-0x5FFF
+    fn read(&self, register: u8) -> u8 {
+        match register {
+            0x08 => self.seconds,
+            0x09 => self.minutes,
+            0x0A => self.hours,
+            0x0B => self.days as u8,
+            _ => {
+                ((self.days >> 8) as u8 & DAY_HIGH_BIT)
+                    | if self.halted { HALT_BIT } else { 0 }
+                    | if self.day_carry { DAY_CARRY_BIT } else { 0 }
+            }
+        }
+    }
+
+    fn write(&mut self, register: u8, byte: u8) {
+        match register {
+            0x08 => self.seconds = byte & 0x3F,
+            0x09 => self.minutes = byte & 0x3F,
+            0x0A => self.hours = byte & 0x1F,
+            0x0B => self.days = (self.days & 0x100) | byte as u16,
+            _ => {
+                self.days = (self.days & 0xFF) | ((byte & DAY_HIGH_BIT) as u16) << 8;
+                self.halted = byte & HALT_BIT != 0;
+                self.day_carry = byte & DAY_CARRY_BIT != 0;
+            }
+        }
+    }
+
+    fn to_bytes(self, bytes: &mut Vec<u8>) {
+        for register in 0x08..=0x0C {
+            bytes.extend_from_slice(&(self.read(register) as u32).to_le_bytes());
+        }
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let mut registers = RtcRegisters::default();
+        for (i, register) in (0x08..=0x0C).enumerate() {
+            registers.write(register, bytes[i * 4]);
+        }
+        registers
+    }
+}
+
+pub struct Rtc {
+    live: RtcRegisters,
+    latched: RtcRegisters,
+    // Clock time the live registers were last brought up to date
+    last_update: u64,
+    clock: Box<dyn Clock>,
+}
+
+impl Rtc {
+    pub fn new(clock: Box<dyn Clock>) -> Self {
+        Rtc {
+            live: RtcRegisters::default(),
+            latched: RtcRegisters::default(),
+            last_update: clock.now(),
+            clock,
+        }
+    }
+
+    fn update(&mut self) {
+        let now = self.clock.now();
+        self.live.advance(now.saturating_sub(self.last_update));
+        self.last_update = now;
+    }
+
+    // Copies the live registers into the ones the game reads
+    pub fn latch(&mut self) {
+        self.update();
+        self.latched = self.live;
+    }
+
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched.read(register)
+    }
+
+    pub fn write(&mut self, register: u8, byte: u8) {
+        self.update();
+        self.live.write(register, byte);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let now = self.clock.now();
+        let mut live = self.live;
+        live.advance(now.saturating_sub(self.last_update));
+
+        let mut bytes = Vec::with_capacity(RTC_DATA_SIZE);
+        live.to_bytes(&mut bytes);
+        self.latched.to_bytes(&mut bytes);
+        bytes.extend_from_slice(&now.to_le_bytes());
+        bytes
+    }
+
+    // Restores the registers saved by to_bytes and catches up on the time
+    // that passed since. Returns false if the data isn't RTC data.
+    pub fn load_bytes(&mut self, bytes: &[u8]) -> bool {
+        let timestamp = match bytes.len() {
+            RTC_DATA_SIZE => {
+                let mut timestamp = [0; 8];
+                timestamp.copy_from_slice(&bytes[40..48]);
+                u64::from_le_bytes(timestamp)
+            }
+            RTC_DATA_SIZE_SHORT => {
+                let mut timestamp = [0; 4];
+                timestamp.copy_from_slice(&bytes[40..44]);
+                u32::from_le_bytes(timestamp) as u64
+            }
+            _ => return false,
+        };
+
+        self.live = RtcRegisters::from_bytes(&bytes[0..20]);
+        self.latched = RtcRegisters::from_bytes(&bytes[20..40]);
+        self.last_update = timestamp;
+        self.update();
+        true
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    // A clock that only moves when the test moves it
+    #[derive(Clone, Default)]
+    pub struct FakeClock(pub Rc<Cell<u64>>);
+
+    impl FakeClock {
+        pub fn advance(&self, seconds: u64) {
+            self.0.set(self.0.get() + seconds);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn advance() {
+        let mut registers = RtcRegisters {
+            seconds: 59,
+            minutes: 59,
+            hours: 23,
+            days: 0x1FF,
+            ..RtcRegisters::default()
+        };
+
+        registers.advance(1);
+        assert_eq!(
+            registers,
+            RtcRegisters {
+                day_carry: true,
+                ..RtcRegisters::default()
+            }
+        );
+
+        registers.advance(2 * 86400 + 3 * 3600 + 4 * 60 + 5);
+        assert_eq!(registers.days, 2);
+        assert_eq!(registers.hours, 3);
+        assert_eq!(registers.minutes, 4);
+        assert_eq!(registers.seconds, 5);
+        assert!(registers.day_carry);
+
+        registers.halted = true;
+        registers.advance(100);
+        assert_eq!(registers.seconds, 5);
+    }
+
+    #[test]
+    fn latch() {
+        let clock = FakeClock::default();
+        let mut rtc = Rtc::new(Box::new(clock.clone()));
+
+        clock.advance(90);
+        assert_eq!(rtc.read(0x08), 0);
+
+        rtc.latch();
+        assert_eq!(rtc.read(0x08), 30);
+        assert_eq!(rtc.read(0x09), 1);
+
+        // Latched values don't move until the next latch
+        clock.advance(10);
+        assert_eq!(rtc.read(0x08), 30);
+        rtc.latch();
+        assert_eq!(rtc.read(0x08), 40);
+    }
+
+    #[test]
+    fn write() {
+        let clock = FakeClock::default();
+        let mut rtc = Rtc::new(Box::new(clock.clone()));
+
+        rtc.write(0x0C, HALT_BIT | DAY_HIGH_BIT);
+        rtc.write(0x0B, 0x23);
+        rtc.write(0x0A, 0x05);
+        clock.advance(1000);
+        rtc.latch();
+        assert_eq!(rtc.read(0x08), 0);
+        assert_eq!(rtc.read(0x0A), 0x05);
+        assert_eq!(rtc.read(0x0B), 0x23);
+        assert_eq!(rtc.read(0x0C), HALT_BIT | DAY_HIGH_BIT);
+
+        rtc.write(0x0C, DAY_CARRY_BIT);
+        clock.advance(61);
+        rtc.latch();
+        assert_eq!(rtc.read(0x08), 1);
+        assert_eq!(rtc.read(0x09), 1);
+        assert_eq!(rtc.read(0x0B), 0x23);
+        assert_eq!(rtc.read(0x0C), DAY_CARRY_BIT);
+    }
+
+    #[test]
+    fn to_and_load_bytes() {
+        let clock = FakeClock(Rc::new(Cell::new(1_000_000)));
+        let mut rtc = Rtc::new(Box::new(clock.clone()));
+        clock.advance(3661);
+        rtc.latch();
+
+        let bytes = rtc.to_bytes();
+        assert_eq!(bytes.len(), RTC_DATA_SIZE);
+        assert_eq!(&bytes[0..4], &[1, 0, 0, 0]);
+        assert_eq!(&bytes[4..8], &[1, 0, 0, 0]);
+        assert_eq!(&bytes[8..12], &[1, 0, 0, 0]);
+        assert_eq!(&bytes[40..48], &1_003_661u64.to_le_bytes());
+
+        // Time keeps going while the game isn't running
+        clock.advance(60);
+        let mut restored = Rtc::new(Box::new(clock.clone()));
+        assert!(restored.load_bytes(&bytes));
+        assert_eq!(restored.read(0x09), 1);
+        restored.latch();
+        assert_eq!(restored.read(0x09), 2);
+
+        assert!(!restored.load_bytes(&bytes[..10]));
+        assert!(restored.load_bytes(&bytes[..RTC_DATA_SIZE_SHORT]));
+    }
+}
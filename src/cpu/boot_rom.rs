@@ -0,0 +1,68 @@
+use std::fs;
+use std::path::Path;
+
+use super::error::BootRomError;
+
+pub const DMG_BOOT_ROM_SIZE: usize = 0x100;
+// The CGB boot ROM leaves a hole at 0x0100-0x01FF for the cartridge header
+pub const CGB_BOOT_ROM_SIZE: usize = 0x900;
+const CGB_HEADER_START: u16 = 0x0100;
+const CGB_HEADER_END: u16 = 0x01FF;
+
+// Writing a non-zero value here unmaps the boot ROM until the next reset
+pub const BOOT_ROM_DISABLE_ADDRESS: u16 = 0xFF50;
+
+pub struct BootRom {
+    data: Vec<u8>,
+}
+
+impl BootRom {
+    pub fn from_bytes(data: Vec<u8>) -> Result<BootRom, BootRomError> {
+        match data.len() {
+            DMG_BOOT_ROM_SIZE | CGB_BOOT_ROM_SIZE => Ok(BootRom { data }),
+            size => Err(BootRomError::InvalidSize(size)),
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<BootRom, BootRomError> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    // The byte the boot ROM overlays at address, or None where the cartridge shows through
+    pub fn read(&self, address: u16) -> Option<u8> {
+        match address {
+            CGB_HEADER_START..=CGB_HEADER_END => None,
+            _ => self.data.get(address as usize).copied(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_bytes() {
+        assert!(BootRom::from_bytes(vec![0; DMG_BOOT_ROM_SIZE]).is_ok());
+        assert!(BootRom::from_bytes(vec![0; CGB_BOOT_ROM_SIZE]).is_ok());
+        assert!(matches!(
+            BootRom::from_bytes(vec![0; 0x200]),
+            Err(BootRomError::InvalidSize(0x200))
+        ));
+    }
+
+    #[test]
+    fn read() {
+        let dmg = BootRom::from_bytes(vec![0x31; DMG_BOOT_ROM_SIZE]).unwrap();
+        assert_eq!(dmg.read(0x0000), Some(0x31));
+        assert_eq!(dmg.read(0x00FF), Some(0x31));
+        assert_eq!(dmg.read(0x0100), None);
+
+        let cgb = BootRom::from_bytes(vec![0x31; CGB_BOOT_ROM_SIZE]).unwrap();
+        assert_eq!(cgb.read(0x00FF), Some(0x31));
+        assert_eq!(cgb.read(0x0150), None);
+        assert_eq!(cgb.read(0x0200), Some(0x31));
+        assert_eq!(cgb.read(0x08FF), Some(0x31));
+        assert_eq!(cgb.read(0x0900), None);
+    }
+}
@@ -1,62 +1,302 @@
+use crate::cartridge::Cartridge;
+use crate::ppu::{Renderer, PPU};
+
+use super::boot_rom::BootRom;
+use super::error::{CPUError, IllegalOpcodePolicy, RegisterSnapshot, ILLEGAL_OPCODES};
 use super::instructions::{
-    ArithmeticTarget, Instruction, LoadByteSource, LoadByteTarget, LoadType, PushPopTarget,
+    AddHLTarget, ArithmeticTarget, BitPosition, IncDecTarget, IncDecWordTarget, Indirect,
+    Instruction, LoadByteSource, LoadByteTarget, LoadType, LoadWordTarget, PrefixTarget,
+    PushPopTarget, RstVector,
 };
+use super::interrupts::{Interrupt, INTERRUPT_FLAG_ADDRESS};
 use super::memorybus::MemoryBus;
+use super::model::{Model, POST_BOOT_PC, POST_BOOT_SP};
 use super::registers::Registers;
 
-struct CPU {
+pub const T_CYCLES_PER_M_CYCLE: u8 = 4;
+
+// Pushing PC and jumping to an interrupt vector takes 5 M-cycles
+const INTERRUPT_DISPATCH_M_CYCLES: u8 = 5;
+
+// A read-modify-write operation on a single byte that may also update the flags
+type ByteOp = fn(&mut CPU, u8) -> u8;
+
+pub struct CPU {
     registers: Registers,
     pc: u16,
     sp: u16,
     mem: MemoryBus,
+    // Interrupt Master Enable
+    ime: bool,
+    // EI only enables interrupts after the instruction following it
+    ime_scheduled: bool,
+    // Set by HALT until an interrupt is pending
+    halted: bool,
+    // Set by STOP until a joypad event
+    stopped: bool,
+    // On DMG, HALT with IME off and an interrupt already pending
+    // doesn't halt and fails to increment PC after the next opcode fetch
+    halt_bug: bool,
+    // Total T-cycles run since the CPU was created
+    cycles: u64,
+    illegal_opcode_policy: IllegalOpcodePolicy,
+    // Set after running an illegal opcode with IllegalOpcodePolicy::LockUp
+    locked_up: bool,
 }
 
-macro_rules! Instruction_ADD {
-    ( $self: ident, $val_to_add:expr ) => {{
-        $self.add($val_to_add);
-    }};
+impl Default for CPU {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CPU {
     pub fn new() -> Self {
+        Self::with_renderer(Renderer::Scanline)
+    }
+
+    pub fn with_renderer(renderer: Renderer) -> Self {
         CPU {
             registers: Registers::new(),
             pc: 0,
             sp: 0,
-            mem: MemoryBus::new(),
+            mem: MemoryBus::with_renderer(renderer),
+            ime: false,
+            ime_scheduled: false,
+            halted: false,
+            stopped: false,
+            halt_bug: false,
+            cycles: 0,
+            illegal_opcode_policy: IllegalOpcodePolicy::LockUp,
+            locked_up: false,
+        }
+    }
+
+    pub fn with_boot_rom(boot_rom: BootRom) -> Self {
+        let mut cpu = Self::new();
+        cpu.load_boot_rom(boot_rom);
+        cpu
+    }
+
+    // Starts at 0x0000 with the boot ROM mapped over the cartridge,
+    // which hands over to the cartridge at 0x0100 once it's done.
+    // Call it on a CPU from with_renderer to boot with the FIFO renderer.
+    pub fn load_boot_rom(&mut self, boot_rom: BootRom) {
+        self.mem.load_boot_rom(boot_rom);
+        self.pc = 0x0000;
+    }
+
+    // Puts the CPU and I/O registers in the state the boot ROM of model leaves
+    // them in and starts at 0x0100. The cartridge should already be inserted
+    // since the DMG flags depend on its header checksum.
+    pub fn skip_boot(&mut self, model: Model) {
+        let header_checksum = self.mem.cartridge().header().header_checksum;
+        let registers = model.post_boot_registers(header_checksum);
+        self.registers.set_af(registers.af);
+        self.registers.set_bc(registers.bc);
+        self.registers.set_de(registers.de);
+        self.registers.set_hl(registers.hl);
+        self.sp = POST_BOOT_SP;
+        self.pc = POST_BOOT_PC;
+
+        self.mem.load_post_boot_state(model);
+    }
+
+    // Runs a single instruction (or interrupt dispatch) and returns
+    // the number of T-cycles it took.
+    pub fn step(&mut self) -> Result<u8, CPUError> {
+        let m_cycles = self.step_m_cycles()?;
+        self.mem.tick(m_cycles);
+        let t_cycles = m_cycles * T_CYCLES_PER_M_CYCLE;
+        self.cycles += t_cycles as u64;
+        Ok(t_cycles)
+    }
+
+    pub fn set_illegal_opcode_policy(&mut self, policy: IllegalOpcodePolicy) {
+        self.illegal_opcode_policy = policy;
+    }
+
+    pub fn registers_snapshot(&self) -> RegisterSnapshot {
+        RegisterSnapshot {
+            af: self.registers.get_af(),
+            bc: self.registers.get_bc(),
+            de: self.registers.get_de(),
+            hl: self.registers.get_hl(),
+            sp: self.sp,
+            pc: self.pc,
         }
     }
 
-    pub fn step(&mut self) {
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    fn step_m_cycles(&mut self) -> Result<u8, CPUError> {
+        // Nothing but a reset gets the CPU out of a lock up
+        if self.locked_up {
+            return Ok(1);
+        }
+
+        if self.stopped {
+            if !self.joypad_requested() {
+                return Ok(1);
+            }
+            self.stopped = false;
+        }
+
+        if self.halted {
+            // HALT wakes up on any pending interrupt, even when IME is off
+            if self.mem.pending_interrupts() == 0 {
+                return Ok(1);
+            }
+            self.halted = false;
+        }
+
+        if self.service_interrupt() {
+            return Ok(INTERRUPT_DISPATCH_M_CYCLES);
+        }
+
+        let enable_ime = self.ime_scheduled;
+
+        let pc = self.pc;
         let mut instruction_byte = self.mem.read_byte(self.pc);
+        if self.halt_bug {
+            // Operands (or the next opcode) are read from the same byte again
+            self.halt_bug = false;
+            self.pc = self.pc.wrapping_sub(1);
+        }
         let prefixed = instruction_byte == 0xCB;
         if prefixed {
             instruction_byte = self.read_next_byte();
         }
 
-        self.pc = if let Some(instruction) = Instruction::from_byte(instruction_byte, prefixed) {
-            self.execute(instruction)
-        } else {
-            let description = format!(
-                "0x{}{:x}",
-                if prefixed { "cb" } else { "" },
-                instruction_byte
-            );
-            panic!("Unknown instruction found for: {}", description)
+        let instruction = match Instruction::from_byte(instruction_byte, prefixed) {
+            Some(instruction) => instruction,
+            None => {
+                // Every opcode decodes except the illegal ones
+                debug_assert!(!prefixed && ILLEGAL_OPCODES.contains(&instruction_byte));
+                self.pc = pc;
+                return match self.illegal_opcode_policy {
+                    IllegalOpcodePolicy::LockUp => {
+                        self.locked_up = true;
+                        Ok(1)
+                    }
+                    IllegalOpcodePolicy::Error => Err(CPUError::IllegalInstruction {
+                        opcode: instruction_byte,
+                        pc,
+                        registers: self.registers_snapshot(),
+                    }),
+                };
+            }
         };
+
+        let m_cycles = instruction.m_cycles(self.registers.f);
+        self.pc = self.execute(instruction);
+
+        // A DI right after EI cancels the scheduled enable
+        if enable_ime && self.ime_scheduled {
+            self.ime = true;
+            self.ime_scheduled = false;
+        }
+
+        Ok(m_cycles)
+    }
+
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge) {
+        self.mem.insert_cartridge(cartridge);
+    }
+
+    pub fn ppu(&self) -> &PPU {
+        self.mem.ppu()
+    }
+
+    // Whether the boot ROM is still running
+    pub fn boot_rom_mapped(&self) -> bool {
+        self.mem.boot_rom_mapped()
+    }
+
+    pub fn cartridge(&self) -> &Cartridge {
+        self.mem.cartridge()
+    }
+
+    pub fn cartridge_mut(&mut self) -> &mut Cartridge {
+        self.mem.cartridge_mut()
+    }
+
+    pub fn request_interrupt(&mut self, interrupt: Interrupt) {
+        self.mem.request_interrupt(interrupt);
+    }
+
+    fn joypad_requested(&self) -> bool {
+        self.mem.read_byte(INTERRUPT_FLAG_ADDRESS) & Interrupt::Joypad.bit() != 0
+    }
+
+    // Jumps to the highest priority pending interrupt if IME is set.
+    fn service_interrupt(&mut self) -> bool {
+        if !self.ime {
+            return false;
+        }
+
+        match Interrupt::highest_priority(self.mem.pending_interrupts()) {
+            Some(interrupt) => {
+                self.ime = false;
+                self.mem.clear_interrupt(interrupt);
+                self.push(self.pc);
+                self.pc = interrupt.vector();
+                true
+            }
+            None => false,
+        }
     }
 
     pub fn execute(&mut self, instruction: Instruction) -> u16 {
         match instruction {
-            Instruction::LD(load_type) => {
-                let LoadType::Byte(target, source) = load_type;
-                self.load(target, source);
+            Instruction::LD(load_type) => match load_type {
+                LoadType::Byte(target, source) => {
+                    self.load(target, source);
 
-                match source {
-                    LoadByteSource::D8 => self.pc.wrapping_add(2),
-                    _ => self.pc.wrapping_add(1),
+                    match source {
+                        LoadByteSource::D8 => self.pc.wrapping_add(2),
+                        _ => self.pc.wrapping_add(1),
+                    }
                 }
-            }
+                LoadType::Word(target) => {
+                    let word = self.read_next_word();
+                    match target {
+                        LoadWordTarget::BC => self.registers.set_bc(word),
+                        LoadWordTarget::DE => self.registers.set_de(word),
+                        LoadWordTarget::HL => self.registers.set_hl(word),
+                        LoadWordTarget::SP => self.sp = word,
+                    }
+                    self.pc.wrapping_add(3)
+                }
+                LoadType::AFromIndirect(indirect) => {
+                    let address = self.indirect_address(indirect);
+                    self.registers.a = self.mem.read_byte(address);
+                    self.indirect_next_pc(indirect)
+                }
+                LoadType::IndirectFromA(indirect) => {
+                    let address = self.indirect_address(indirect);
+                    self.mem.write_byte(address, self.registers.a);
+                    self.indirect_next_pc(indirect)
+                }
+                LoadType::SPFromHL => {
+                    self.sp = self.registers.get_hl();
+                    self.pc.wrapping_add(1)
+                }
+                LoadType::HLFromSPN => {
+                    let value = self.add_sp_signed(self.read_next_byte());
+                    self.registers.set_hl(value);
+                    self.pc.wrapping_add(2)
+                }
+                LoadType::IndirectFromSP => {
+                    let address = self.read_next_word();
+                    self.mem.write_byte(address, (self.sp & 0xFF) as u8);
+                    self.mem
+                        .write_byte(address.wrapping_add(1), ((self.sp & 0xFF00) >> 8) as u8);
+                    self.pc.wrapping_add(3)
+                }
+            },
             Instruction::PUSH(target) => {
                 match target {
                     PushPopTarget::AF => self.push(self.registers.get_af()),
@@ -77,47 +317,220 @@ impl CPU {
                 self.pc.wrapping_add(1)
             }
             Instruction::ADD(target) => {
+                self.add(self.arithmetic_target_value(target));
+                self.arithmetic_next_pc(target)
+            }
+            Instruction::ADC(target) => {
+                self.adc(self.arithmetic_target_value(target));
+                self.arithmetic_next_pc(target)
+            }
+            Instruction::SUB(target) => {
+                self.sub(self.arithmetic_target_value(target));
+                self.arithmetic_next_pc(target)
+            }
+            Instruction::SBC(target) => {
+                self.sbc(self.arithmetic_target_value(target));
+                self.arithmetic_next_pc(target)
+            }
+            Instruction::AND(target) => {
+                self.and(self.arithmetic_target_value(target));
+                self.arithmetic_next_pc(target)
+            }
+            Instruction::OR(target) => {
+                self.or(self.arithmetic_target_value(target));
+                self.arithmetic_next_pc(target)
+            }
+            Instruction::XOR(target) => {
+                self.xor(self.arithmetic_target_value(target));
+                self.arithmetic_next_pc(target)
+            }
+            Instruction::CP(target) => {
+                self.cp(self.arithmetic_target_value(target));
+                self.arithmetic_next_pc(target)
+            }
+            Instruction::INC(target) => {
+                let value = self.inc(self.inc_dec_target_value(target));
+                self.set_inc_dec_target_value(target, value);
+                self.pc.wrapping_add(1)
+            }
+            Instruction::DEC(target) => {
+                let value = self.dec(self.inc_dec_target_value(target));
+                self.set_inc_dec_target_value(target, value);
+                self.pc.wrapping_add(1)
+            }
+            Instruction::INCW(target) => {
+                match target {
+                    IncDecWordTarget::BC => self
+                        .registers
+                        .set_bc(self.registers.get_bc().wrapping_add(1)),
+                    IncDecWordTarget::DE => self
+                        .registers
+                        .set_de(self.registers.get_de().wrapping_add(1)),
+                    IncDecWordTarget::HL => self
+                        .registers
+                        .set_hl(self.registers.get_hl().wrapping_add(1)),
+                    IncDecWordTarget::SP => self.sp = self.sp.wrapping_add(1),
+                }
+                self.pc.wrapping_add(1)
+            }
+            Instruction::DECW(target) => {
                 match target {
-                    ArithmeticTarget::A => Instruction_ADD!(self, self.registers.a),
-                    ArithmeticTarget::B => Instruction_ADD!(self, self.registers.b),
-                    ArithmeticTarget::C => Instruction_ADD!(self, self.registers.c),
-                    ArithmeticTarget::D => Instruction_ADD!(self, self.registers.d),
-                    ArithmeticTarget::E => Instruction_ADD!(self, self.registers.e),
-                    ArithmeticTarget::H => Instruction_ADD!(self, self.registers.h),
-                    ArithmeticTarget::L => Instruction_ADD!(self, self.registers.l),
+                    IncDecWordTarget::BC => self
+                        .registers
+                        .set_bc(self.registers.get_bc().wrapping_sub(1)),
+                    IncDecWordTarget::DE => self
+                        .registers
+                        .set_de(self.registers.get_de().wrapping_sub(1)),
+                    IncDecWordTarget::HL => self
+                        .registers
+                        .set_hl(self.registers.get_hl().wrapping_sub(1)),
+                    IncDecWordTarget::SP => self.sp = self.sp.wrapping_sub(1),
+                }
+                self.pc.wrapping_add(1)
+            }
+            Instruction::ADDHL(target) => {
+                let value = match target {
+                    AddHLTarget::BC => self.registers.get_bc(),
+                    AddHLTarget::DE => self.registers.get_de(),
+                    AddHLTarget::HL => self.registers.get_hl(),
+                    AddHLTarget::SP => self.sp,
+                };
+                self.add_hl(value);
+                self.pc.wrapping_add(1)
+            }
+            Instruction::ADDSP() => {
+                self.sp = self.add_sp_signed(self.read_next_byte());
+                self.pc.wrapping_add(2)
+            }
+            Instruction::DAA() => {
+                self.daa();
+                self.pc.wrapping_add(1)
+            }
+            Instruction::CPL() => {
+                self.registers.a = !self.registers.a;
+                self.registers.f.subtract = true;
+                self.registers.f.half_carry = true;
+                self.pc.wrapping_add(1)
+            }
+            Instruction::CCF() => {
+                self.registers.f.subtract = false;
+                self.registers.f.half_carry = false;
+                self.registers.f.carry = !self.registers.f.carry;
+                self.pc.wrapping_add(1)
+            }
+            Instruction::SCF() => {
+                self.registers.f.subtract = false;
+                self.registers.f.half_carry = false;
+                self.registers.f.carry = true;
+                self.pc.wrapping_add(1)
+            }
+            Instruction::RLCA() => self.accumulator_rotate(Self::rlc),
+            Instruction::RLA() => self.accumulator_rotate(Self::rl),
+            Instruction::RRCA() => self.accumulator_rotate(Self::rrc),
+            Instruction::RRA() => self.accumulator_rotate(Self::rr),
+            Instruction::HALT() => {
+                if !self.ime && self.mem.pending_interrupts() != 0 {
+                    self.halt_bug = true;
+                } else {
+                    self.halted = true;
                 }
                 self.pc.wrapping_add(1)
             }
+            Instruction::STOP() => {
+                // TODO: switch speed through KEY1 once CGB mode exists
+                self.stopped = true;
+                // STOP is 2 bytes wide (0x10 0x00)
+                self.pc.wrapping_add(2)
+            }
+            Instruction::DI() => {
+                self.ime = false;
+                self.ime_scheduled = false;
+                self.pc.wrapping_add(1)
+            }
+            Instruction::EI() => {
+                self.ime_scheduled = true;
+                self.pc.wrapping_add(1)
+            }
             Instruction::NOP() => self.pc.wrapping_add(1),
+            Instruction::RLC(target) => self.prefix_op(target, Self::rlc),
+            Instruction::RRC(target) => self.prefix_op(target, Self::rrc),
+            Instruction::RL(target) => self.prefix_op(target, Self::rl),
+            Instruction::RR(target) => self.prefix_op(target, Self::rr),
+            Instruction::SLA(target) => self.prefix_op(target, Self::sla),
+            Instruction::SRA(target) => self.prefix_op(target, Self::sra),
+            Instruction::SWAP(target) => self.prefix_op(target, Self::swap),
+            Instruction::SRL(target) => self.prefix_op(target, Self::srl),
+            Instruction::BIT(bit, target) => {
+                self.bit(bit, self.prefix_target_value(target));
+                self.pc.wrapping_add(2)
+            }
+            Instruction::RES(bit, target) => {
+                let value = self.prefix_target_value(target) & !(1 << u8::from(bit));
+                self.set_prefix_target_value(target, value);
+                self.pc.wrapping_add(2)
+            }
+            Instruction::SET(bit, target) => {
+                let value = self.prefix_target_value(target) | (1 << u8::from(bit));
+                self.set_prefix_target_value(target, value);
+                self.pc.wrapping_add(2)
+            }
             Instruction::JP(test) => {
                 let condition = test.condition_depending_on_flags_reg(self.registers.f);
                 self.jump(condition)
             }
+            Instruction::JR(test) => {
+                let condition = test.condition_depending_on_flags_reg(self.registers.f);
+                self.jump_relative(condition)
+            }
+            Instruction::JPHL() => self.registers.get_hl(),
             Instruction::CALL(test) => {
                 let condition = test.condition_depending_on_flags_reg(self.registers.f);
                 self.call(condition)
             }
+            Instruction::RST(vector) => self.rst(vector),
             Instruction::RET(test) => {
                 let condition = test.condition_depending_on_flags_reg(self.registers.f);
                 self.return_(condition)
             }
-            _ => {
-                panic!("Instruction: {:?} not implemented", instruction);
+            Instruction::RETI() => {
+                self.ime = true;
+                self.return_(true)
             }
         }
     }
 
     fn read_next_byte(&self) -> u8 {
-        self.mem.read_byte(self.pc + 1)
+        self.mem.read_byte(self.pc.wrapping_add(1))
     }
 
     fn read_next_word(&self) -> u16 {
-        let lsb = self.mem.read_byte(self.pc + 1) as u16;
-        let msb = self.mem.read_byte(self.pc + 2) as u16;
+        let lsb = self.mem.read_byte(self.pc.wrapping_add(1)) as u16;
+        let msb = self.mem.read_byte(self.pc.wrapping_add(2)) as u16;
 
         (msb << 8) | lsb
     }
 
+    fn arithmetic_target_value(&self, target: ArithmeticTarget) -> u8 {
+        match target {
+            ArithmeticTarget::A => self.registers.a,
+            ArithmeticTarget::B => self.registers.b,
+            ArithmeticTarget::C => self.registers.c,
+            ArithmeticTarget::D => self.registers.d,
+            ArithmeticTarget::E => self.registers.e,
+            ArithmeticTarget::H => self.registers.h,
+            ArithmeticTarget::L => self.registers.l,
+            ArithmeticTarget::D8 => self.read_next_byte(),
+            ArithmeticTarget::HLI => self.mem.read_byte(self.registers.get_hl()),
+        }
+    }
+
+    fn arithmetic_next_pc(&self, target: ArithmeticTarget) -> u16 {
+        match target {
+            ArithmeticTarget::D8 => self.pc.wrapping_add(2),
+            _ => self.pc.wrapping_add(1),
+        }
+    }
+
     fn add(&mut self, value: u8) {
         let (new_value, did_overflow) = self.registers.a.overflowing_add(value);
 
@@ -133,12 +546,287 @@ impl CPU {
         self.registers.a = new_value;
     }
 
+    fn adc(&mut self, value: u8) {
+        let carry = if self.registers.f.carry { 1 } else { 0 };
+        let sum = self.registers.a as u16 + value as u16 + carry as u16;
+        let new_value = sum as u8;
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = sum > 0xFF;
+        // Same as ADD except the carry bit also counts towards the lower nibble
+        self.registers.f.half_carry = (self.registers.a & 0xF) + (value & 0xF) + carry > 0xF;
+
+        self.registers.a = new_value;
+    }
+
+    fn sub(&mut self, value: u8) {
+        self.registers.a = self.subtract_from_a(value, false);
+    }
+
+    fn sbc(&mut self, value: u8) {
+        self.registers.a = self.subtract_from_a(value, self.registers.f.carry);
+    }
+
+    fn and(&mut self, value: u8) {
+        self.registers.a &= value;
+
+        self.registers.f.zero = self.registers.a == 0;
+        self.registers.f.subtract = false;
+        // AND always sets the half carry flag
+        self.registers.f.half_carry = true;
+        self.registers.f.carry = false;
+    }
+
+    fn or(&mut self, value: u8) {
+        self.registers.a |= value;
+
+        self.registers.f.zero = self.registers.a == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = false;
+    }
+
+    fn xor(&mut self, value: u8) {
+        self.registers.a ^= value;
+
+        self.registers.f.zero = self.registers.a == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = false;
+    }
+
+    fn cp(&mut self, value: u8) {
+        // CP is a SUB that throws away the result and only keeps the flags
+        self.subtract_from_a(value, false);
+    }
+
+    // Computes A - value - carry, sets the flags and returns the result
+    // without storing it so SUB, SBC and CP can share it.
+    fn subtract_from_a(&mut self, value: u8, carry: bool) -> u8 {
+        let carry = if carry { 1 } else { 0 };
+        let a = self.registers.a;
+        let new_value = a.wrapping_sub(value).wrapping_sub(carry);
+
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = true;
+        // Half Carry is set if the lower nibble had to borrow from the upper nibble
+        self.registers.f.half_carry = (a & 0xF) < (value & 0xF) + carry;
+        // Carry is set if the whole subtraction had to borrow
+        self.registers.f.carry = (a as u16) < value as u16 + carry as u16;
+
+        new_value
+    }
+
+    fn inc_dec_target_value(&self, target: IncDecTarget) -> u8 {
+        match target {
+            IncDecTarget::A => self.registers.a,
+            IncDecTarget::B => self.registers.b,
+            IncDecTarget::C => self.registers.c,
+            IncDecTarget::D => self.registers.d,
+            IncDecTarget::E => self.registers.e,
+            IncDecTarget::H => self.registers.h,
+            IncDecTarget::L => self.registers.l,
+            IncDecTarget::HLI => self.mem.read_byte(self.registers.get_hl()),
+        }
+    }
+
+    fn set_inc_dec_target_value(&mut self, target: IncDecTarget, value: u8) {
+        match target {
+            IncDecTarget::A => self.registers.a = value,
+            IncDecTarget::B => self.registers.b = value,
+            IncDecTarget::C => self.registers.c = value,
+            IncDecTarget::D => self.registers.d = value,
+            IncDecTarget::E => self.registers.e = value,
+            IncDecTarget::H => self.registers.h = value,
+            IncDecTarget::L => self.registers.l = value,
+            IncDecTarget::HLI => self.mem.write_byte(self.registers.get_hl(), value),
+        }
+    }
+
+    // 8 bit INC leaves the carry flag untouched
+    fn inc(&mut self, value: u8) -> u8 {
+        let result = value.wrapping_add(1);
+
+        self.registers.f.zero = result == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = value & 0xF == 0xF;
+
+        result
+    }
+
+    // 8 bit DEC leaves the carry flag untouched
+    fn dec(&mut self, value: u8) -> u8 {
+        let result = value.wrapping_sub(1);
+
+        self.registers.f.zero = result == 0;
+        self.registers.f.subtract = true;
+        self.registers.f.half_carry = value & 0xF == 0;
+
+        result
+    }
+
+    fn add_hl(&mut self, value: u16) {
+        let hl = self.registers.get_hl();
+        let (new_value, did_overflow) = hl.overflowing_add(value);
+
+        // zero is left untouched
+        self.registers.f.subtract = false;
+        self.registers.f.carry = did_overflow;
+        // For 16 bit adds the half carry is the carry out of bit 11
+        self.registers.f.half_carry = (hl & 0xFFF) + (value & 0xFFF) > 0xFFF;
+
+        self.registers.set_hl(new_value);
+    }
+
+    // Adjusts A back into binary coded decimal after an ADD/ADC/SUB/SBC
+    // of two BCD numbers, using the subtract and half carry flags to know
+    // which operation happened and which digits overflowed.
+    fn daa(&mut self) {
+        let mut a = self.registers.a;
+        let mut carry = self.registers.f.carry;
+
+        if self.registers.f.subtract {
+            if carry {
+                a = a.wrapping_sub(0x60);
+            }
+            if self.registers.f.half_carry {
+                a = a.wrapping_sub(0x06);
+            }
+        } else {
+            if carry || a > 0x99 {
+                a = a.wrapping_add(0x60);
+                carry = true;
+            }
+            if self.registers.f.half_carry || (a & 0xF) > 0x9 {
+                a = a.wrapping_add(0x06);
+            }
+        }
+
+        self.registers.f.zero = a == 0;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+        // subtract is left untouched
+
+        self.registers.a = a;
+    }
+
+    // RLCA, RLA, RRCA and RRA are 1 byte versions of the prefixed
+    // rotates on A that always reset the zero flag.
+    fn accumulator_rotate(&mut self, op: ByteOp) -> u16 {
+        self.registers.a = op(self, self.registers.a);
+        self.registers.f.zero = false;
+        self.pc.wrapping_add(1)
+    }
+
+    fn prefix_target_value(&self, target: PrefixTarget) -> u8 {
+        match target {
+            PrefixTarget::A => self.registers.a,
+            PrefixTarget::B => self.registers.b,
+            PrefixTarget::C => self.registers.c,
+            PrefixTarget::D => self.registers.d,
+            PrefixTarget::E => self.registers.e,
+            PrefixTarget::H => self.registers.h,
+            PrefixTarget::L => self.registers.l,
+            PrefixTarget::HLI => self.mem.read_byte(self.registers.get_hl()),
+        }
+    }
+
+    fn set_prefix_target_value(&mut self, target: PrefixTarget, value: u8) {
+        match target {
+            PrefixTarget::A => self.registers.a = value,
+            PrefixTarget::B => self.registers.b = value,
+            PrefixTarget::C => self.registers.c = value,
+            PrefixTarget::D => self.registers.d = value,
+            PrefixTarget::E => self.registers.e = value,
+            PrefixTarget::H => self.registers.h = value,
+            PrefixTarget::L => self.registers.l = value,
+            PrefixTarget::HLI => self.mem.write_byte(self.registers.get_hl(), value),
+        }
+    }
+
+    // Runs a read-modify-write prefix instruction on its target.
+    // Prefixed instructions are 2 bytes wide (0xCB and the opcode).
+    fn prefix_op(&mut self, target: PrefixTarget, op: ByteOp) -> u16 {
+        let value = op(self, self.prefix_target_value(target));
+        self.set_prefix_target_value(target, value);
+        self.pc.wrapping_add(2)
+    }
+
+    // Sets the flags shared by all of the rotate and shift instructions
+    fn set_shift_flags(&mut self, result: u8, carry: bool) {
+        self.registers.f.zero = result == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+    }
+
+    fn rlc(&mut self, value: u8) -> u8 {
+        let result = value.rotate_left(1);
+        self.set_shift_flags(result, value & 0x80 != 0);
+        result
+    }
+
+    fn rrc(&mut self, value: u8) -> u8 {
+        let result = value.rotate_right(1);
+        self.set_shift_flags(result, value & 0x01 != 0);
+        result
+    }
+
+    // Rotates left through the carry flag
+    fn rl(&mut self, value: u8) -> u8 {
+        let carry_in = if self.registers.f.carry { 1 } else { 0 };
+        let result = (value << 1) | carry_in;
+        self.set_shift_flags(result, value & 0x80 != 0);
+        result
+    }
+
+    // Rotates right through the carry flag
+    fn rr(&mut self, value: u8) -> u8 {
+        let carry_in = if self.registers.f.carry { 0x80 } else { 0 };
+        let result = (value >> 1) | carry_in;
+        self.set_shift_flags(result, value & 0x01 != 0);
+        result
+    }
+
+    fn sla(&mut self, value: u8) -> u8 {
+        let result = value << 1;
+        self.set_shift_flags(result, value & 0x80 != 0);
+        result
+    }
+
+    // Arithmetic shift right keeps the sign bit (bit 7) as it was
+    fn sra(&mut self, value: u8) -> u8 {
+        let result = (value >> 1) | (value & 0x80);
+        self.set_shift_flags(result, value & 0x01 != 0);
+        result
+    }
+
+    fn swap(&mut self, value: u8) -> u8 {
+        let result = value.rotate_left(4);
+        self.set_shift_flags(result, false);
+        result
+    }
+
+    fn srl(&mut self, value: u8) -> u8 {
+        let result = value >> 1;
+        self.set_shift_flags(result, value & 0x01 != 0);
+        result
+    }
+
+    fn bit(&mut self, bit: BitPosition, value: u8) {
+        self.registers.f.zero = (value >> u8::from(bit)) & 0b1 == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = true;
+        // carry is left untouched
+    }
+
     fn jump(&self, should_jump: bool) -> u16 {
         if should_jump {
             // Gameboy is little endian so read pc + 2 as most significant bit
             // and pc + 1 as least significant bit
-            let least_significant_byte = self.mem.read_byte(self.pc + 1) as u16;
-            let most_significant_byte = self.mem.read_byte(self.pc + 2) as u16;
+            let least_significant_byte = self.mem.read_byte(self.pc.wrapping_add(1)) as u16;
+            let most_significant_byte = self.mem.read_byte(self.pc.wrapping_add(2)) as u16;
             (most_significant_byte << 8) | least_significant_byte
         } else {
             // If we don't jump we need to still move the program
@@ -148,6 +836,17 @@ impl CPU {
         }
     }
 
+    fn jump_relative(&self, should_jump: bool) -> u16 {
+        // JR is 2 bytes wide and the offset is relative to the next instruction
+        let next_pc = self.pc.wrapping_add(2);
+        if should_jump {
+            let offset = self.read_next_byte() as i8;
+            next_pc.wrapping_add(offset as u16)
+        } else {
+            next_pc
+        }
+    }
+
     fn load(&mut self, target: LoadByteTarget, source: LoadByteSource) {
         let source_value = match source {
             LoadByteSource::A => self.registers.a,
@@ -172,6 +871,49 @@ impl CPU {
         };
     }
 
+    // Resolves the address an indirect load reads from or writes to.
+    // (HL+) and (HL-) adjust HL after the address has been taken.
+    fn indirect_address(&mut self, indirect: Indirect) -> u16 {
+        match indirect {
+            Indirect::BC => self.registers.get_bc(),
+            Indirect::DE => self.registers.get_de(),
+            Indirect::HLPlus => {
+                let hl = self.registers.get_hl();
+                self.registers.set_hl(hl.wrapping_add(1));
+                hl
+            }
+            Indirect::HLMinus => {
+                let hl = self.registers.get_hl();
+                self.registers.set_hl(hl.wrapping_sub(1));
+                hl
+            }
+            Indirect::Word => self.read_next_word(),
+            Indirect::LastByte => 0xFF00 | self.registers.c as u16,
+            Indirect::Byte => 0xFF00 | self.read_next_byte() as u16,
+        }
+    }
+
+    fn indirect_next_pc(&self, indirect: Indirect) -> u16 {
+        match indirect {
+            Indirect::Word => self.pc.wrapping_add(3),
+            Indirect::Byte => self.pc.wrapping_add(2),
+            _ => self.pc.wrapping_add(1),
+        }
+    }
+
+    // Returns SP + the signed byte value. The flags are set as if
+    // the value was added unsigned to the lower byte of SP.
+    fn add_sp_signed(&mut self, value: u8) -> u16 {
+        let sp_low = (self.sp & 0xFF) as u8;
+
+        self.registers.f.zero = false;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = (sp_low & 0xF) + (value & 0xF) > 0xF;
+        self.registers.f.carry = sp_low as u16 + value as u16 > 0xFF;
+
+        self.sp.wrapping_add(value as i8 as u16)
+    }
+
     fn push(&mut self, value: u16) {
         self.sp = self.sp.wrapping_sub(1);
         self.mem.write_byte(self.sp, ((value & 0xFF00) >> 8) as u8);
@@ -200,6 +942,11 @@ impl CPU {
         }
     }
 
+    fn rst(&mut self, vector: RstVector) -> u16 {
+        self.push(self.pc.wrapping_add(1));
+        u16::from(vector)
+    }
+
     fn return_(&mut self, should_jump: bool) -> u16 {
         if should_jump {
             self.pop()
@@ -231,7 +978,7 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.pc = 0x0000;
-        cpu.mem.write_byte(0x0001, 0x8F);
+        cpu.mem.write_rom_byte(0x0001, 0x8F);
         assert_eq!(cpu.read_next_byte(), 0x8F);
     }
 
@@ -240,8 +987,8 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.pc = 0x0000;
-        cpu.mem.write_byte(0x0001, 0xFF);
-        cpu.mem.write_byte(0x0002, 0xAA);
+        cpu.mem.write_rom_byte(0x0001, 0xFF);
+        cpu.mem.write_rom_byte(0x0002, 0xAA);
         assert_eq!(cpu.read_next_word(), 0xAAFF);
     }
 
@@ -267,48 +1014,945 @@ mod tests {
     }
 
     #[test]
-    fn jump() {
+    fn adc() {
         let mut cpu = CPU::new();
 
-        cpu.pc = 0x0000;
-        cpu.mem.write_byte(0x0001, 0xFF);
-        cpu.mem.write_byte(0x0002, 0xAA);
+        cpu.registers.a = 0xE1;
+        cpu.registers.f.carry = true;
+        cpu.adc(0x0F);
+        let mut expected_f = FlagsRegister::new();
+        expected_f.half_carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0xF1);
 
-        // false branch
-        assert_eq!(cpu.jump(false), 0x0003);
-        // true branch
-        assert_eq!(cpu.jump(true), 0xAAFF);
+        cpu.registers.f.carry = true;
+        cpu.adc(0x0E);
+        expected_f.zero = true;
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0x00);
     }
 
-    // TODO:
-    // #[test]
-    // fn load() {
-    //     let mut cpu = CPU::new();
-    // }
-
     #[test]
-    fn push() {
+    fn sub() {
         let mut cpu = CPU::new();
 
-        cpu.sp = 0x0002;
-        cpu.push(0xAAFF);
+        cpu.registers.a = 0x3E;
+        cpu.sub(0x0F);
+        let mut expected_f = FlagsRegister::new();
+        expected_f.subtract = true;
+        expected_f.half_carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0x2F);
+
+        cpu.sub(0x2F);
+        expected_f.zero = true;
+        expected_f.half_carry = false;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0x00);
+
+        cpu.sub(0x01);
+        expected_f.zero = false;
+        expected_f.half_carry = true;
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0xFF);
+    }
+
+    #[test]
+    fn sbc() {
+        let mut cpu = CPU::new();
+
+        cpu.registers.a = 0x3B;
+        cpu.registers.f.carry = true;
+        cpu.sbc(0x2A);
+        let mut expected_f = FlagsRegister::new();
+        expected_f.subtract = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0x10);
+
+        cpu.registers.f.carry = true;
+        cpu.sbc(0x10);
+        expected_f.half_carry = true;
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0xFF);
+    }
+
+    #[test]
+    fn and() {
+        let mut cpu = CPU::new();
+
+        cpu.registers.a = 0x5A;
+        cpu.registers.f.carry = true;
+        cpu.and(0x3F);
+        let mut expected_f = FlagsRegister::new();
+        expected_f.half_carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0x1A);
+
+        cpu.and(0x00);
+        expected_f.zero = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0x00);
+    }
+
+    #[test]
+    fn or() {
+        let mut cpu = CPU::new();
+
+        cpu.registers.f.carry = true;
+        cpu.or(0x00);
+        let mut expected_f = FlagsRegister::new();
+        expected_f.zero = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0x00);
+
+        cpu.registers.a = 0x5A;
+        cpu.or(0x0F);
+        expected_f.zero = false;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0x5F);
+    }
+
+    #[test]
+    fn xor() {
+        let mut cpu = CPU::new();
+
+        cpu.registers.a = 0xFF;
+        cpu.xor(0x0F);
+        let mut expected_f = FlagsRegister::new();
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0xF0);
+
+        // XOR A is the common idiom to clear A
+        cpu.xor(cpu.registers.a);
+        expected_f.zero = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0x00);
+    }
+
+    #[test]
+    fn cp() {
+        let mut cpu = CPU::new();
+
+        cpu.registers.a = 0x3C;
+        cpu.cp(0x3C);
+        let mut expected_f = FlagsRegister::new();
+        expected_f.zero = true;
+        expected_f.subtract = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0x3C);
+
+        cpu.cp(0x40);
+        expected_f.zero = false;
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.a, 0x3C);
+    }
+
+    #[test]
+    fn execute_arithmetic_sources() {
+        let mut cpu = CPU::new();
+
+        cpu.pc = 0x0100;
+        cpu.mem.write_rom_byte(0x0101, 0x12);
+        assert_eq!(cpu.execute(Instruction::ADD(ArithmeticTarget::D8)), 0x0102);
+        assert_eq!(cpu.registers.a, 0x12);
+
+        cpu.registers.set_hl(0xC000);
+        cpu.mem.write_byte(0xC000, 0x02);
+        assert_eq!(cpu.execute(Instruction::SUB(ArithmeticTarget::HLI)), 0x0101);
+        assert_eq!(cpu.registers.a, 0x10);
+
+        cpu.registers.b = 0x10;
+        assert_eq!(cpu.execute(Instruction::XOR(ArithmeticTarget::B)), 0x0101);
+        assert_eq!(cpu.registers.a, 0x00);
+        assert!(cpu.registers.f.zero);
+    }
+
+    #[test]
+    fn inc() {
+        let mut cpu = CPU::new();
+        cpu.registers.f.carry = true;
+
+        assert_eq!(cpu.inc(0x0F), 0x10);
+        let mut expected_f = FlagsRegister::new();
+        expected_f.half_carry = true;
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        assert_eq!(cpu.inc(0xFF), 0x00);
+        expected_f.zero = true;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        assert_eq!(cpu.inc(0x01), 0x02);
+        expected_f.zero = false;
+        expected_f.half_carry = false;
+        assert_eq!(cpu.registers.f, expected_f);
+    }
+
+    #[test]
+    fn dec() {
+        let mut cpu = CPU::new();
+
+        assert_eq!(cpu.dec(0x10), 0x0F);
+        let mut expected_f = FlagsRegister::new();
+        expected_f.subtract = true;
+        expected_f.half_carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        assert_eq!(cpu.dec(0x01), 0x00);
+        expected_f.zero = true;
+        expected_f.half_carry = false;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        assert_eq!(cpu.dec(0x00), 0xFF);
+        expected_f.zero = false;
+        expected_f.half_carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+    }
+
+    #[test]
+    fn add_hl() {
+        let mut cpu = CPU::new();
+        cpu.registers.f.zero = true;
+
+        cpu.registers.set_hl(0x0FFF);
+        cpu.add_hl(0x0001);
+        let mut expected_f = FlagsRegister::new();
+        expected_f.zero = true;
+        expected_f.half_carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.get_hl(), 0x1000);
+
+        cpu.add_hl(0xF000);
+        expected_f.half_carry = false;
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+        assert_eq!(cpu.registers.get_hl(), 0x0000);
+    }
+
+    #[test]
+    fn execute_inc_dec() {
+        let mut cpu = CPU::new();
+
+        cpu.pc = 0x0100;
+        cpu.registers.set_bc(0xFFFF);
+        assert_eq!(cpu.execute(Instruction::INCW(IncDecWordTarget::BC)), 0x0101);
+        assert_eq!(cpu.registers.get_bc(), 0x0000);
+        // 16 bit INC/DEC don't touch the flags
+        assert_eq!(cpu.registers.f, FlagsRegister::new());
+
+        cpu.execute(Instruction::DECW(IncDecWordTarget::SP));
+        assert_eq!(cpu.sp, 0xFFFF);
+
+        cpu.registers.set_hl(0xC000);
+        cpu.mem.write_byte(0xC000, 0x41);
+        cpu.execute(Instruction::INC(IncDecTarget::HLI));
+        assert_eq!(cpu.mem.read_byte(0xC000), 0x42);
+
+        cpu.registers.a = 0x01;
+        cpu.execute(Instruction::DEC(IncDecTarget::A));
+        assert_eq!(cpu.registers.a, 0x00);
+        assert!(cpu.registers.f.zero);
+    }
+
+    #[test]
+    fn execute_add_sp() {
+        let mut cpu = CPU::new();
+
+        cpu.pc = 0x0100;
+        cpu.sp = 0xFFF8;
+        cpu.mem.write_rom_byte(0x0101, 0xF8);
+        assert_eq!(cpu.execute(Instruction::ADDSP()), 0x0102);
+        assert_eq!(cpu.sp, 0xFFF0);
+        let mut expected_f = FlagsRegister::new();
+        expected_f.half_carry = true;
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+    }
+
+    // Packs a number from 0-99 into a BCD byte
+    fn to_bcd(value: u8) -> u8 {
+        ((value / 10) << 4) | (value % 10)
+    }
+
+    #[test]
+    fn daa_after_add() {
+        let mut cpu = CPU::new();
+
+        for x in 0..100 {
+            for y in 0..100 {
+                cpu.registers.a = to_bcd(x);
+                cpu.add(to_bcd(y));
+                cpu.daa();
+
+                let sum = x as u16 + y as u16;
+                let result = (sum % 100) as u8;
+                assert_eq!(cpu.registers.a, to_bcd(result), "{} + {}", x, y);
+                assert_eq!(cpu.registers.f.carry, sum >= 100, "{} + {}", x, y);
+                assert_eq!(cpu.registers.f.zero, result == 0, "{} + {}", x, y);
+                assert!(!cpu.registers.f.subtract);
+                assert!(!cpu.registers.f.half_carry);
+            }
+        }
+    }
+
+    #[test]
+    fn daa_after_sub() {
+        let mut cpu = CPU::new();
+
+        for x in 0..100 {
+            for y in 0..100 {
+                cpu.registers.a = to_bcd(x);
+                cpu.sub(to_bcd(y));
+                cpu.daa();
+
+                let difference = (x as i16 - y as i16).rem_euclid(100) as u8;
+                assert_eq!(cpu.registers.a, to_bcd(difference), "{} - {}", x, y);
+                assert_eq!(cpu.registers.f.carry, x < y, "{} - {}", x, y);
+                assert_eq!(cpu.registers.f.zero, difference == 0, "{} - {}", x, y);
+                assert!(cpu.registers.f.subtract);
+                assert!(!cpu.registers.f.half_carry);
+            }
+        }
+    }
+
+    #[test]
+    fn daa_flags() {
+        let mut cpu = CPU::new();
+
+        for a in 0..=0xFF {
+            for flags in 0..0x10u8 {
+                cpu.registers.a = a;
+                cpu.registers.f = FlagsRegister::from(flags << 4);
+                let before = cpu.registers.f;
+                cpu.daa();
+
+                let f = cpu.registers.f;
+                assert_eq!(f.zero, cpu.registers.a == 0, "a: {:#x} f: {:?}", a, before);
+                assert_eq!(f.subtract, before.subtract, "a: {:#x} f: {:?}", a, before);
+                assert!(!f.half_carry, "a: {:#x} f: {:?}", a, before);
+                // DAA never clears a carry that was already set
+                if before.carry {
+                    assert!(f.carry, "a: {:#x} f: {:?}", a, before);
+                }
+                // and it only sets one after an addition
+                if before.subtract && !before.carry {
+                    assert!(!f.carry, "a: {:#x} f: {:?}", a, before);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn daa_values() {
+        let mut cpu = CPU::new();
+
+        // (A, flags before, A after, flags after)
+        let cases: [(u8, u8, u8, u8); 8] = [
+            // 0x15 + 0x27
+            (0x3C, 0x00, 0x42, 0x00),
+            // 0x09 + 0x09 carries out of the low nibble
+            (0x12, 0x20, 0x18, 0x00),
+            (0x99, 0x00, 0x99, 0x00),
+            (0x9A, 0x00, 0x00, 0x90),
+            // 0x90 + 0x10
+            (0xA0, 0x00, 0x00, 0x90),
+            // 0x42 - 0x15
+            (0x2D, 0x60, 0x27, 0x40),
+            // 0x10 - 0x20
+            (0xF0, 0x50, 0x90, 0x50),
+            (0x00, 0x70, 0x9A, 0x50),
+        ];
+        for &(a, flags, result, result_flags) in cases.iter() {
+            cpu.registers.a = a;
+            cpu.registers.f = FlagsRegister::from(flags);
+            cpu.daa();
+            assert_eq!(cpu.registers.a, result, "a: {:#x} f: {:#x}", a, flags);
+            assert_eq!(
+                u8::from(cpu.registers.f),
+                result_flags,
+                "a: {:#x} f: {:#x}",
+                a,
+                flags
+            );
+        }
+    }
+
+    #[test]
+    fn execute_cpl_ccf_scf() {
+        let mut cpu = CPU::new();
+        cpu.registers.f.zero = true;
+
+        cpu.registers.a = 0x35;
+        cpu.execute(Instruction::CPL());
+        assert_eq!(cpu.registers.a, 0xCA);
+        let mut expected_f = FlagsRegister::new();
+        expected_f.zero = true;
+        expected_f.subtract = true;
+        expected_f.half_carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        cpu.execute(Instruction::SCF());
+        expected_f.subtract = false;
+        expected_f.half_carry = false;
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        cpu.execute(Instruction::CCF());
+        expected_f.carry = false;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        cpu.execute(Instruction::CCF());
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+    }
+
+    #[test]
+    fn execute_accumulator_rotates() {
+        let rotates: [(u8, ByteOp); 4] = [
+            (0x07, CPU::rlc),
+            (0x17, CPU::rl),
+            (0x0F, CPU::rrc),
+            (0x1F, CPU::rr),
+        ];
+
+        for &(opcode, op) in rotates.iter() {
+            for a in 0..=0xFF {
+                for &carry in [false, true].iter() {
+                    let mut cpu = CPU::new();
+                    cpu.registers.a = a;
+                    cpu.registers.f.carry = carry;
+
+                    let mut prefixed = CPU::new();
+                    prefixed.registers.f.carry = carry;
+                    let expected = op(&mut prefixed, a);
+
+                    let instruction = Instruction::from_byte(opcode, false).unwrap();
+                    assert_eq!(cpu.execute(instruction), 0x0001);
+                    assert_eq!(cpu.registers.a, expected);
+                    // Same as the prefixed rotate except zero is always reset
+                    prefixed.registers.f.zero = false;
+                    assert_eq!(cpu.registers.f, prefixed.registers.f);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rotates() {
+        let mut cpu = CPU::new();
+        let mut expected_f = FlagsRegister::new();
+
+        assert_eq!(cpu.rlc(0x85), 0x0B);
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        assert_eq!(cpu.rrc(0x01), 0x80);
+        assert_eq!(cpu.registers.f, expected_f);
+
+        // carry is set from the rrc above so it is rotated into bit 0
+        assert_eq!(cpu.rl(0x80), 0x01);
+        assert_eq!(cpu.registers.f, expected_f);
+
+        assert_eq!(cpu.rr(0x00), 0x80);
+        expected_f.carry = false;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        assert_eq!(cpu.rl(0x00), 0x00);
+        expected_f.zero = true;
+        assert_eq!(cpu.registers.f, expected_f);
+    }
+
+    #[test]
+    fn shifts() {
+        let mut cpu = CPU::new();
+        let mut expected_f = FlagsRegister::new();
+
+        assert_eq!(cpu.sla(0x80), 0x00);
+        expected_f.zero = true;
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        assert_eq!(cpu.sra(0x8A), 0xC5);
+        expected_f.zero = false;
+        expected_f.carry = false;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        assert_eq!(cpu.srl(0x01), 0x00);
+        expected_f.zero = true;
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        assert_eq!(cpu.swap(0xF1), 0x1F);
+        expected_f.zero = false;
+        expected_f.carry = false;
+        assert_eq!(cpu.registers.f, expected_f);
+    }
+
+    #[test]
+    fn bit() {
+        let mut cpu = CPU::new();
+        cpu.registers.f.carry = true;
+
+        cpu.bit(BitPosition::B7, 0x80);
+        let mut expected_f = FlagsRegister::new();
+        expected_f.half_carry = true;
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        cpu.bit(BitPosition::B0, 0x80);
+        expected_f.zero = true;
+        assert_eq!(cpu.registers.f, expected_f);
+    }
+
+    #[test]
+    fn execute_prefixed() {
+        let mut cpu = CPU::new();
+
+        cpu.pc = 0x0100;
+        cpu.registers.set_hl(0xC000);
+        cpu.mem.write_byte(0xC000, 0x0F);
+
+        let next_pc = cpu.execute(Instruction::SWAP(PrefixTarget::HLI));
+        assert_eq!(next_pc, 0x0102);
+        assert_eq!(cpu.mem.read_byte(0xC000), 0xF0);
+
+        cpu.execute(Instruction::RES(BitPosition::B7, PrefixTarget::HLI));
+        assert_eq!(cpu.mem.read_byte(0xC000), 0x70);
+
+        cpu.execute(Instruction::SET(BitPosition::B0, PrefixTarget::B));
+        assert_eq!(cpu.registers.b, 0x01);
+    }
+
+    #[test]
+    fn step_prefixed() {
+        let mut cpu = CPU::new();
+
+        // SET 2,A
+        cpu.mem.write_rom_byte(0x0000, 0xCB);
+        cpu.mem.write_rom_byte(0x0001, 0xD7);
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.registers.a, 0x04);
+        assert_eq!(cpu.pc, 0x0002);
+    }
+
+    #[test]
+    fn step_ei_delay() {
+        let mut cpu = CPU::new();
+        cpu.sp = 0xD000;
+        cpu.mem.write_byte(0xFFFF, Interrupt::Timer.bit());
+        cpu.request_interrupt(Interrupt::Timer);
+
+        // EI, NOP, NOP
+        cpu.mem.write_rom_byte(0x0000, 0xFB);
+        cpu.step().unwrap();
+        assert!(!cpu.ime);
+        assert_eq!(cpu.pc, 0x0001);
+
+        // the instruction after EI still runs before the interrupt
+        cpu.step().unwrap();
+        assert!(cpu.ime);
+        assert_eq!(cpu.pc, 0x0002);
+
+        cpu.step().unwrap();
+        assert!(!cpu.ime);
+        assert_eq!(cpu.pc, 0x0050);
+        assert_eq!(cpu.pop(), 0x0002);
+        assert_eq!(cpu.mem.pending_interrupts(), 0x00);
+    }
+
+    #[test]
+    fn step_di_cancels_ei() {
+        let mut cpu = CPU::new();
+
+        // EI, DI
+        cpu.mem.write_rom_byte(0x0000, 0xFB);
+        cpu.mem.write_rom_byte(0x0001, 0xF3);
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert!(!cpu.ime);
+
+        cpu.step().unwrap();
+        assert!(!cpu.ime);
+    }
+
+    #[test]
+    fn service_interrupt() {
+        let mut cpu = CPU::new();
+        cpu.pc = 0x1234;
+        cpu.sp = 0xD000;
+
+        // Nothing happens while IME is off
+        cpu.mem.write_byte(0xFFFF, 0x1F);
+        cpu.request_interrupt(Interrupt::Joypad);
+        cpu.request_interrupt(Interrupt::LCDStat);
+        assert!(!cpu.service_interrupt());
+
+        // Highest priority goes first
+        cpu.ime = true;
+        assert!(cpu.service_interrupt());
+        assert_eq!(cpu.pc, 0x0048);
+        assert_eq!(cpu.sp, 0xCFFE);
+        assert!(!cpu.ime);
+        assert_eq!(cpu.mem.pending_interrupts(), Interrupt::Joypad.bit());
+
+        // Interrupts that aren't enabled in IE are ignored
+        cpu.ime = true;
+        cpu.mem.write_byte(0xFFFF, 0x00);
+        assert!(!cpu.service_interrupt());
+        assert_eq!(cpu.pc, 0x0048);
+    }
+
+    #[test]
+    fn step_cycles() {
+        let mut cpu = CPU::new();
+        cpu.sp = 0xD000;
+
+        // NOP
+        cpu.mem.write_rom_byte(0x0000, 0x00);
+        assert_eq!(cpu.step(), Ok(4));
+
+        // JR NZ,+2 taken then JR Z,+0 not taken
+        cpu.mem.write_rom_byte(0x0001, 0x20);
+        cpu.mem.write_rom_byte(0x0002, 0x02);
+        cpu.mem.write_rom_byte(0x0005, 0x28);
+        assert_eq!(cpu.step(), Ok(12));
+        assert_eq!(cpu.pc, 0x0005);
+        assert_eq!(cpu.step(), Ok(8));
+
+        // CALL nn
+        cpu.mem.write_rom_byte(0x0007, 0xCD);
+        cpu.mem.write_rom_byte(0x0008, 0x00);
+        cpu.mem.write_rom_byte(0x0009, 0x01);
+        assert_eq!(cpu.step(), Ok(24));
+
+        // Interrupt dispatch
+        cpu.ime = true;
+        cpu.mem.write_byte(0xFFFF, Interrupt::VBlank.bit());
+        cpu.request_interrupt(Interrupt::VBlank);
+        assert_eq!(cpu.step(), Ok(20));
+
+        assert_eq!(cpu.cycles(), 4 + 12 + 8 + 24 + 20);
+    }
+
+    #[test]
+    fn step_illegal_opcode_lock_up() {
+        let mut cpu = CPU::new();
+        cpu.ime = true;
+        cpu.mem.write_byte(0xFFFF, 0x1F);
+
+        cpu.mem.write_rom_byte(0x0000, 0xD3);
+        assert_eq!(cpu.step(), Ok(4));
+        assert!(cpu.locked_up);
+
+        // Interrupts don't get the CPU out of it
+        cpu.request_interrupt(Interrupt::VBlank);
+        assert_eq!(cpu.step(), Ok(4));
+        assert_eq!(cpu.pc, 0x0000);
+    }
+
+    #[test]
+    fn step_illegal_opcode_error() {
+        let mut cpu = CPU::new();
+        cpu.set_illegal_opcode_policy(IllegalOpcodePolicy::Error);
+
+        cpu.pc = 0x0150;
+        cpu.sp = 0xFFFE;
+        cpu.registers.set_bc(0x1234);
+        cpu.mem.write_rom_byte(0x0150, 0xFD);
+
+        let expected = CPUError::IllegalInstruction {
+            opcode: 0xFD,
+            pc: 0x0150,
+            registers: RegisterSnapshot {
+                af: 0x0000,
+                bc: 0x1234,
+                de: 0x0000,
+                hl: 0x0000,
+                sp: 0xFFFE,
+                pc: 0x0150,
+            },
+        };
+        assert_eq!(cpu.step(), Err(expected));
+        assert_eq!(cpu.pc, 0x0150);
+        assert!(!cpu.locked_up);
+
+        for &opcode in ILLEGAL_OPCODES.iter() {
+            cpu.mem.write_rom_byte(0x0150, opcode);
+            assert!(matches!(
+                cpu.step(),
+                Err(CPUError::IllegalInstruction { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn step_halt() {
+        let mut cpu = CPU::new();
+        cpu.sp = 0xD000;
+        cpu.ime = true;
+        cpu.mem.write_byte(0xFFFF, Interrupt::VBlank.bit());
+
+        // HALT
+        cpu.mem.write_rom_byte(0x0000, 0x76);
+        cpu.step().unwrap();
+        assert!(cpu.halted);
+        assert_eq!(cpu.pc, 0x0001);
+
+        cpu.step().unwrap();
+        assert!(cpu.halted);
+        assert_eq!(cpu.pc, 0x0001);
+
+        cpu.request_interrupt(Interrupt::VBlank);
+        cpu.step().unwrap();
+        assert!(!cpu.halted);
+        assert_eq!(cpu.pc, 0x0040);
+        assert_eq!(cpu.pop(), 0x0001);
+    }
+
+    #[test]
+    fn step_halt_wakes_with_ime_off() {
+        let mut cpu = CPU::new();
+        cpu.mem.write_byte(0xFFFF, Interrupt::Serial.bit());
+
+        // HALT, INC A
+        cpu.mem.write_rom_byte(0x0000, 0x76);
+        cpu.mem.write_rom_byte(0x0001, 0x3C);
+        cpu.step().unwrap();
+        assert!(cpu.halted);
+
+        // Interrupts that aren't enabled don't wake the CPU
+        cpu.request_interrupt(Interrupt::Timer);
+        cpu.step().unwrap();
+        assert!(cpu.halted);
+
+        // Without IME the interrupt isn't serviced, execution just continues
+        cpu.request_interrupt(Interrupt::Serial);
+        cpu.step().unwrap();
+        assert!(!cpu.halted);
+        assert_eq!(cpu.pc, 0x0002);
+        assert_eq!(cpu.registers.a, 0x01);
+        assert_eq!(cpu.mem.pending_interrupts(), Interrupt::Serial.bit());
+    }
+
+    #[test]
+    fn step_halt_bug() {
+        let mut cpu = CPU::new();
+        cpu.mem.write_byte(0xFFFF, Interrupt::VBlank.bit());
+        cpu.request_interrupt(Interrupt::VBlank);
+
+        // HALT, LD A,d8 (0x3E) 0x14
+        cpu.mem.write_rom_byte(0x0000, 0x76);
+        cpu.mem.write_rom_byte(0x0001, 0x3E);
+        cpu.mem.write_rom_byte(0x0002, 0x14);
+        cpu.step().unwrap();
+        assert!(!cpu.halted);
+        assert_eq!(cpu.pc, 0x0001);
+
+        // 0x3E is read twice so it becomes the operand of the load
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.a, 0x3E);
+        assert_eq!(cpu.pc, 0x0002);
 
-        assert_eq!(cpu.sp, 0x0000);
-        assert_eq!(cpu.mem.read_byte(0x0001), 0xAA);
-        assert_eq!(cpu.mem.read_byte(0x0000), 0xFF);
+        // and 0x14 (INC D) runs as its own instruction
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.d, 0x01);
+        assert_eq!(cpu.pc, 0x0003);
+    }
+
+    #[test]
+    fn step_halt_bug_at_zero() {
+        let mut cpu = CPU::new();
+        cpu.halt_bug = true;
+
+        // PC wraps to 0xFFFF so the operand is read from 0x0000 again
+        cpu.mem.write_rom_byte(0x0000, 0x3E);
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.a, 0x3E);
+        assert_eq!(cpu.pc, 0x0001);
+    }
+
+    #[test]
+    fn step_stop() {
+        let mut cpu = CPU::new();
+
+        // STOP
+        cpu.mem.write_rom_byte(0x0000, 0x10);
+        cpu.step().unwrap();
+        assert!(cpu.stopped);
+        assert_eq!(cpu.pc, 0x0002);
+
+        // Other interrupts don't leave STOP mode
+        cpu.mem.write_byte(0xFFFF, 0x1F);
+        cpu.request_interrupt(Interrupt::VBlank);
+        cpu.step().unwrap();
+        assert!(cpu.stopped);
+        assert_eq!(cpu.pc, 0x0002);
+
+        cpu.request_interrupt(Interrupt::Joypad);
+        cpu.step().unwrap();
+        assert!(!cpu.stopped);
+        assert_eq!(cpu.pc, 0x0003);
+    }
+
+    #[test]
+    fn jump() {
+        let mut cpu = CPU::new();
+
+        cpu.pc = 0x0000;
+        cpu.mem.write_rom_byte(0x0001, 0xFF);
+        cpu.mem.write_rom_byte(0x0002, 0xAA);
+
+        // false branch
+        assert_eq!(cpu.jump(false), 0x0003);
+        // true branch
+        assert_eq!(cpu.jump(true), 0xAAFF);
+    }
+
+    #[test]
+    fn jump_relative() {
+        let mut cpu = CPU::new();
+
+        cpu.pc = 0x0100;
+        cpu.mem.write_rom_byte(0x0101, 0x05);
+        assert_eq!(cpu.jump_relative(false), 0x0102);
+        assert_eq!(cpu.jump_relative(true), 0x0107);
+
+        // negative offsets jump backwards
+        cpu.mem.write_rom_byte(0x0101, 0xFE);
+        assert_eq!(cpu.jump_relative(true), 0x0100);
+    }
+
+    #[test]
+    fn execute_jump_hl() {
+        let mut cpu = CPU::new();
+
+        cpu.registers.set_hl(0xC123);
+        assert_eq!(cpu.execute(Instruction::JPHL()), 0xC123);
+    }
+
+    #[test]
+    fn load() {
+        let mut cpu = CPU::new();
+
+        cpu.registers.b = 0x42;
+        cpu.load(LoadByteTarget::A, LoadByteSource::B);
+        assert_eq!(cpu.registers.a, 0x42);
+
+        cpu.registers.set_hl(0xC000);
+        cpu.load(LoadByteTarget::HLI, LoadByteSource::A);
+        assert_eq!(cpu.mem.read_byte(0xC000), 0x42);
+
+        cpu.pc = 0x0100;
+        cpu.mem.write_rom_byte(0x0101, 0x99);
+        cpu.load(LoadByteTarget::E, LoadByteSource::D8);
+        assert_eq!(cpu.registers.e, 0x99);
+    }
+
+    #[test]
+    fn execute_load_word() {
+        let mut cpu = CPU::new();
+
+        cpu.pc = 0x0100;
+        cpu.mem.write_rom_byte(0x0101, 0xFE);
+        cpu.mem.write_rom_byte(0x0102, 0xFF);
+        let next_pc = cpu.execute(Instruction::LD(LoadType::Word(LoadWordTarget::SP)));
+        assert_eq!(next_pc, 0x0103);
+        assert_eq!(cpu.sp, 0xFFFE);
+
+        cpu.registers.set_hl(0x1234);
+        assert_eq!(cpu.execute(Instruction::LD(LoadType::SPFromHL)), 0x0101);
+        assert_eq!(cpu.sp, 0x1234);
+
+        cpu.mem.write_rom_byte(0x0101, 0x00);
+        cpu.mem.write_rom_byte(0x0102, 0xC0);
+        assert_eq!(
+            cpu.execute(Instruction::LD(LoadType::IndirectFromSP)),
+            0x0103
+        );
+        assert_eq!(cpu.mem.read_byte(0xC000), 0x34);
+        assert_eq!(cpu.mem.read_byte(0xC001), 0x12);
+    }
+
+    #[test]
+    fn execute_load_indirect() {
+        let mut cpu = CPU::new();
+
+        cpu.pc = 0x0100;
+        cpu.registers.a = 0x11;
+        cpu.registers.set_hl(0xC000);
+        let next_pc = cpu.execute(Instruction::LD(LoadType::IndirectFromA(Indirect::HLPlus)));
+        assert_eq!(next_pc, 0x0101);
+        assert_eq!(cpu.mem.read_byte(0xC000), 0x11);
+        assert_eq!(cpu.registers.get_hl(), 0xC001);
+
+        cpu.mem.write_byte(0xC001, 0x22);
+        cpu.execute(Instruction::LD(LoadType::AFromIndirect(Indirect::HLMinus)));
+        assert_eq!(cpu.registers.a, 0x22);
+        assert_eq!(cpu.registers.get_hl(), 0xC000);
+
+        cpu.mem.write_rom_byte(0x0101, 0x80);
+        let next_pc = cpu.execute(Instruction::LD(LoadType::IndirectFromA(Indirect::Byte)));
+        assert_eq!(next_pc, 0x0102);
+        assert_eq!(cpu.mem.read_byte(0xFF80), 0x22);
+
+        cpu.registers.c = 0x80;
+        cpu.registers.a = 0x00;
+        cpu.execute(Instruction::LD(LoadType::AFromIndirect(Indirect::LastByte)));
+        assert_eq!(cpu.registers.a, 0x22);
+    }
+
+    #[test]
+    fn execute_load_hl_from_sp_n() {
+        let mut cpu = CPU::new();
+
+        cpu.pc = 0x0100;
+        cpu.sp = 0xFFF8;
+        cpu.mem.write_rom_byte(0x0101, 0x08);
+        assert_eq!(cpu.execute(Instruction::LD(LoadType::HLFromSPN)), 0x0102);
+        assert_eq!(cpu.registers.get_hl(), 0x0000);
+        let mut expected_f = FlagsRegister::new();
+        expected_f.half_carry = true;
+        expected_f.carry = true;
+        assert_eq!(cpu.registers.f, expected_f);
+
+        // negative offsets
+        cpu.sp = 0x0001;
+        cpu.mem.write_rom_byte(0x0101, 0xFE);
+        cpu.execute(Instruction::LD(LoadType::HLFromSPN));
+        assert_eq!(cpu.registers.get_hl(), 0xFFFF);
+        expected_f.half_carry = false;
+        expected_f.carry = false;
+        assert_eq!(cpu.registers.f, expected_f);
+    }
+
+    #[test]
+    fn push() {
+        let mut cpu = CPU::new();
+
+        cpu.sp = 0xC002;
+        cpu.push(0xAAFF);
+
+        assert_eq!(cpu.sp, 0xC000);
+        assert_eq!(cpu.mem.read_byte(0xC001), 0xAA);
+        assert_eq!(cpu.mem.read_byte(0xC000), 0xFF);
     }
 
     #[test]
     fn pop() {
         let mut cpu = CPU::new();
 
-        cpu.mem.write_byte(0x0001, 0xAA);
-        cpu.mem.write_byte(0x0000, 0xFF);
-        cpu.sp = 0x0000;
+        cpu.mem.write_byte(0xC001, 0xAA);
+        cpu.mem.write_byte(0xC000, 0xFF);
+        cpu.sp = 0xC000;
         let actual = cpu.pop();
 
         assert_eq!(actual, 0xAAFF);
-        assert_eq!(cpu.sp, 0x0002);
+        assert_eq!(cpu.sp, 0xC002);
     }
 
     #[test]
@@ -323,16 +1967,41 @@ mod tests {
         let mut cpu = CPU::new();
 
         cpu.pc = 0x0100;
-        cpu.mem.write_byte(0x0101, 0xFF);
-        cpu.mem.write_byte(0x0102, 0xAA);
+        cpu.mem.write_rom_byte(0x0101, 0xFF);
+        cpu.mem.write_rom_byte(0x0102, 0xAA);
 
-        cpu.sp = 0x0010;
+        cpu.sp = 0xC010;
 
         let actual = cpu.call(true);
 
         assert_eq!(actual, 0xAAFF);
-        assert_eq!(cpu.mem.read_byte(0x000F), 0x01);
-        assert_eq!(cpu.mem.read_byte(0x000E), 0x03);
+        assert_eq!(cpu.mem.read_byte(0xC00F), 0x01);
+        assert_eq!(cpu.mem.read_byte(0xC00E), 0x03);
+    }
+
+    #[test]
+    fn rst() {
+        let mut cpu = CPU::new();
+
+        cpu.pc = 0x0100;
+        cpu.sp = 0xC010;
+
+        assert_eq!(cpu.rst(RstVector::H38), 0x0038);
+        assert_eq!(cpu.sp, 0xC00E);
+        assert_eq!(cpu.mem.read_byte(0xC00F), 0x01);
+        assert_eq!(cpu.mem.read_byte(0xC00E), 0x01);
+    }
+
+    #[test]
+    fn execute_reti() {
+        let mut cpu = CPU::new();
+
+        cpu.sp = 0xC00E;
+        cpu.mem.write_byte(0xC00F, 0x01);
+        cpu.mem.write_byte(0xC00E, 0x03);
+
+        assert_eq!(cpu.execute(Instruction::RETI()), 0x0103);
+        assert!(cpu.ime);
     }
 
     #[test]
@@ -346,10 +2015,57 @@ mod tests {
     fn return_should_jump() {
         let mut cpu = CPU::new();
 
-        cpu.sp = 0x000E;
-        cpu.mem.write_byte(0x000F, 0x01);
-        cpu.mem.write_byte(0x000E, 0x03);
+        cpu.sp = 0xC00E;
+        cpu.mem.write_byte(0xC00F, 0x01);
+        cpu.mem.write_byte(0xC00E, 0x03);
 
         assert_eq!(cpu.return_(true), 0x0103);
     }
+
+    #[test]
+    fn boot_rom_hands_over_to_cartridge() {
+        // NOPs, then unmap the boot ROM from the last two instructions like the real one does
+        let mut boot_rom = vec![0x00; 0x100];
+        boot_rom[0xFC..].copy_from_slice(&[0x3E, 0x01, 0xE0, 0x50]);
+        let mut cpu = CPU::with_renderer(Renderer::Fifo);
+        cpu.load_boot_rom(BootRom::from_bytes(boot_rom).unwrap());
+        cpu.mem.write_rom_byte(0x0000, 0xAA);
+
+        assert_eq!(cpu.pc, 0x0000);
+        assert_eq!(cpu.mem.read_byte(0x0000), 0x00);
+
+        while cpu.pc != 0x0100 {
+            cpu.step().unwrap();
+        }
+        assert!(!cpu.mem.boot_rom_mapped());
+        assert_eq!(cpu.mem.read_byte(0x0000), 0xAA);
+    }
+
+    #[test]
+    fn skip_boot() {
+        let mut cpu = CPU::new();
+        cpu.skip_boot(Model::DMG);
+
+        let registers = cpu.registers_snapshot();
+        // The blank cartridge has a zero header checksum
+        assert_eq!(registers.af, 0x0180);
+        assert_eq!(registers.bc, 0x0013);
+        assert_eq!(registers.de, 0x00D8);
+        assert_eq!(registers.hl, 0x014D);
+        assert_eq!(registers.sp, 0xFFFE);
+        assert_eq!(registers.pc, 0x0100);
+
+        assert_eq!(cpu.mem.read_byte(0xFF40), 0x91);
+        assert_eq!(cpu.mem.read_byte(0xFF47), 0xFC);
+        assert_eq!(cpu.mem.read_byte(0xFF04), 0xAB);
+        assert_eq!(cpu.mem.read_byte(INTERRUPT_FLAG_ADDRESS), 0xE1);
+        assert_eq!(cpu.mem.read_byte(0xFFFF), 0x00);
+
+        let mut cpu = CPU::new();
+        cpu.skip_boot(Model::CGB);
+        assert_eq!(cpu.registers_snapshot().af, 0x1180);
+        assert_eq!(cpu.mem.read_byte(0xFF02), 0x7F);
+        // CGB only registers aren't emulated and stay unmapped
+        assert_eq!(cpu.mem.read_byte(0xFF70), 0xFF);
+    }
 }
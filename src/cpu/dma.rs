@@ -0,0 +1,173 @@
+pub const DMA_ADDRESS: u16 = 0xFF46;
+
+// One byte is copied to OAM per M-cycle
+pub const DMA_LENGTH: u16 = 0xA0;
+// The transfer starts one M-cycle after the register is written
+const DMA_STARTUP_M_CYCLES: u8 = 1;
+
+// Sources in 0xE000-0xFFFF read from WRAM like echo RAM does
+const ECHO_SOURCE_START: u16 = 0xE000;
+const ECHO_SOURCE_OFFSET: u16 = 0x2000;
+
+const VRAM_BUS_START: u16 = 0x8000;
+const VRAM_BUS_END: u16 = 0x9FFF;
+
+// Which bus an address is on, which decides whether it conflicts with a DMA source
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Bus {
+    // ROM, external RAM and WRAM
+    External,
+    VRAM,
+}
+
+impl Bus {
+    // The bus for an address below OAM, the rest isn't shared with DMA
+    pub fn of(address: u16) -> Bus {
+        match address {
+            VRAM_BUS_START..=VRAM_BUS_END => Bus::VRAM,
+            _ => Bus::External,
+        }
+    }
+}
+
+// The OAM DMA controller
+pub struct OamDma {
+    // The last value written to 0xFF46
+    register: u8,
+    // A transfer waiting out its startup delay
+    pending: Option<(u16, u8)>,
+    // The source of the running transfer and how many bytes it has copied
+    transfer: Option<(u16, u16)>,
+    // The byte on the source bus, which is what the CPU sees if it reads that bus
+    last_byte: u8,
+}
+
+impl OamDma {
+    pub fn new() -> Self {
+        OamDma {
+            register: 0,
+            pending: None,
+            transfer: None,
+            last_byte: 0xFF,
+        }
+    }
+
+    pub fn read_register(&self) -> u8 {
+        self.register
+    }
+
+    // Sets 0xFF46 without starting a transfer
+    pub fn set_register(&mut self, byte: u8) {
+        self.register = byte;
+    }
+
+    // Starts copying 0xXX00-0xXX9F to OAM, restarting any running transfer
+    pub fn start(&mut self, byte: u8) {
+        self.register = byte;
+        self.pending = Some(((byte as u16) << 8, DMA_STARTUP_M_CYCLES));
+    }
+
+    // Whether a transfer is copying bytes. While it is the CPU is locked out of
+    // OAM and the source's bus, the other bus, HRAM and I/O stay usable.
+    pub fn active(&self) -> bool {
+        self.transfer.is_some()
+    }
+
+    // The bus the running transfer reads from
+    pub fn source_bus(&self) -> Option<Bus> {
+        self.transfer.map(|(source, _)| Bus::of(source))
+    }
+
+    pub fn last_byte(&self) -> u8 {
+        self.last_byte
+    }
+
+    // Advances the transfer by one M-cycle and returns the (source address,
+    // OAM offset) of the byte to copy in this cycle, if any
+    pub fn tick(&mut self) -> Option<(u16, u16)> {
+        let copy = self.transfer.map(|(source, copied)| {
+            let mut address = source + copied;
+            if address >= ECHO_SOURCE_START {
+                address -= ECHO_SOURCE_OFFSET;
+            }
+            (address, copied)
+        });
+        if let Some((source, copied)) = self.transfer {
+            self.transfer = if copied + 1 < DMA_LENGTH {
+                Some((source, copied + 1))
+            } else {
+                None
+            };
+        }
+
+        if let Some((source, delay)) = self.pending {
+            self.pending = if delay > 1 {
+                Some((source, delay - 1))
+            } else {
+                self.transfer = Some((source, 0));
+                None
+            };
+        }
+
+        copy
+    }
+
+    // Remembers the byte that was just copied
+    pub fn set_last_byte(&mut self, byte: u8) {
+        self.last_byte = byte;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer() {
+        let mut dma = OamDma::new();
+        dma.start(0xC1);
+        assert_eq!(dma.read_register(), 0xC1);
+
+        // Nothing is copied during the startup cycle
+        assert_eq!(dma.tick(), None);
+        assert!(dma.active());
+
+        for i in 0..DMA_LENGTH {
+            assert_eq!(dma.tick(), Some((0xC100 + i, i)));
+        }
+        assert!(!dma.active());
+        assert_eq!(dma.tick(), None);
+    }
+
+    #[test]
+    fn echo_source() {
+        let mut dma = OamDma::new();
+        dma.start(0xFE);
+        dma.tick();
+        assert_eq!(dma.tick(), Some((0xDE00, 0)));
+    }
+
+    #[test]
+    fn restart() {
+        let mut dma = OamDma::new();
+        dma.start(0xC0);
+        for _ in 0..10 {
+            dma.tick();
+        }
+
+        // The old transfer keeps going until the new one starts
+        dma.start(0x80);
+        assert_eq!(dma.tick(), Some((0xC009, 9)));
+        assert_eq!(dma.source_bus(), Some(Bus::VRAM));
+        assert_eq!(dma.tick(), Some((0x8000, 0)));
+    }
+
+    #[test]
+    fn bus() {
+        assert_eq!(Bus::of(0x0000), Bus::External);
+        assert_eq!(Bus::of(0x8000), Bus::VRAM);
+        assert_eq!(Bus::of(0xA000), Bus::External);
+        assert_eq!(Bus::of(0xC000), Bus::External);
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt;
+use std::io;
+
+// The register values at the time an error happened
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RegisterSnapshot {
+    pub af: u16,
+    pub bc: u16,
+    pub de: u16,
+    pub hl: u16,
+    pub sp: u16,
+    pub pc: u16,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CPUError {
+    // One of the opcodes that lock up a real DMG, see IllegalOpcodePolicy
+    IllegalInstruction {
+        opcode: u8,
+        pc: u16,
+        registers: RegisterSnapshot,
+    },
+}
+
+impl fmt::Display for CPUError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CPUError::IllegalInstruction {
+                opcode,
+                pc,
+                registers,
+            } => write!(
+                f,
+                "Illegal instruction found for: 0x{:02x} at 0x{:04x} ({:?})",
+                opcode, pc, registers
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CPUError {}
+
+// What to do when the CPU runs into one of the illegal DMG opcodes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IllegalOpcodePolicy {
+    // Hang forever like the hardware does
+    LockUp,
+    // Return a CPUError::IllegalInstruction from CPU::step
+    Error,
+}
+
+// Opcodes that don't exist on the DMG and hang the CPU
+pub const ILLEGAL_OPCODES: [u8; 11] = [
+    0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD,
+];
+
+#[derive(Debug)]
+pub enum BootRomError {
+    Io(io::Error),
+    // Boot ROMs are either 256 bytes (DMG) or 2304 bytes (CGB)
+    InvalidSize(usize),
+}
+
+impl fmt::Display for BootRomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BootRomError::Io(err) => write!(f, "Failed to read boot ROM: {}", err),
+            BootRomError::InvalidSize(size) => write!(f, "Invalid boot ROM size: {} bytes", size),
+        }
+    }
+}
+
+impl std::error::Error for BootRomError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BootRomError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl std::convert::From<io::Error> for BootRomError {
+    fn from(err: io::Error) -> Self {
+        BootRomError::Io(err)
+    }
+}
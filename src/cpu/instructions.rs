@@ -6,11 +6,52 @@ pub enum Instruction {
     PUSH(PushPopTarget),
     POP(PushPopTarget),
     ADD(ArithmeticTarget),
+    ADC(ArithmeticTarget),
+    SUB(ArithmeticTarget),
+    SBC(ArithmeticTarget),
+    AND(ArithmeticTarget),
+    OR(ArithmeticTarget),
+    XOR(ArithmeticTarget),
+    CP(ArithmeticTarget),
+    INC(IncDecTarget),
+    DEC(IncDecTarget),
+    INCW(IncDecWordTarget),
+    DECW(IncDecWordTarget),
+    ADDHL(AddHLTarget),
+    ADDSP(),
+    DAA(),
+    CPL(),
+    CCF(),
+    SCF(),
+    RLCA(),
+    RLA(),
+    RRCA(),
+    RRA(),
+    DI(),
+    EI(),
     NOP(),
+
+    // Prefix instructions
+    RLC(PrefixTarget),
+    RRC(PrefixTarget),
+    RL(PrefixTarget),
+    RR(PrefixTarget),
+    SLA(PrefixTarget),
+    SRA(PrefixTarget),
+    SWAP(PrefixTarget),
+    SRL(PrefixTarget),
+    BIT(BitPosition, PrefixTarget),
+    RES(BitPosition, PrefixTarget),
+    SET(BitPosition, PrefixTarget),
     HALT(),
+    STOP(),
     JP(JumpTest),
+    JR(JumpTest),
+    JPHL(),
     CALL(JumpTest),
+    RST(RstVector),
     RET(JumpTest),
+    RETI(),
 }
 
 #[derive(Debug)]
@@ -21,7 +62,7 @@ pub enum PushPopTarget {
     HL,
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug)]
 pub enum ArithmeticTarget {
     A,
     B,
@@ -30,6 +71,107 @@ pub enum ArithmeticTarget {
     E,
     H,
     L,
+    D8,
+    HLI,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IncDecTarget {
+    A,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    HLI,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IncDecWordTarget {
+    BC,
+    DE,
+    HL,
+    SP,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AddHLTarget {
+    BC,
+    DE,
+    HL,
+    SP,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PrefixTarget {
+    A,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    HLI,
+}
+
+impl PrefixTarget {
+    // The lower 3 bits of every prefixed opcode select the operand
+    fn from_byte(byte: u8) -> PrefixTarget {
+        match byte & 0x07 {
+            0x00 => PrefixTarget::B,
+            0x01 => PrefixTarget::C,
+            0x02 => PrefixTarget::D,
+            0x03 => PrefixTarget::E,
+            0x04 => PrefixTarget::H,
+            0x05 => PrefixTarget::L,
+            0x06 => PrefixTarget::HLI,
+            _ => PrefixTarget::A,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BitPosition {
+    B0,
+    B1,
+    B2,
+    B3,
+    B4,
+    B5,
+    B6,
+    B7,
+}
+
+impl BitPosition {
+    // Bits 3-5 of the BIT/RES/SET opcodes select the bit to operate on
+    fn from_byte(byte: u8) -> BitPosition {
+        match (byte >> 3) & 0x07 {
+            0 => BitPosition::B0,
+            1 => BitPosition::B1,
+            2 => BitPosition::B2,
+            3 => BitPosition::B3,
+            4 => BitPosition::B4,
+            5 => BitPosition::B5,
+            6 => BitPosition::B6,
+            _ => BitPosition::B7,
+        }
+    }
+}
+
+impl std::convert::From<BitPosition> for u8 {
+    fn from(position: BitPosition) -> u8 {
+        match position {
+            BitPosition::B0 => 0,
+            BitPosition::B1 => 1,
+            BitPosition::B2 => 2,
+            BitPosition::B3 => 3,
+            BitPosition::B4 => 4,
+            BitPosition::B5 => 5,
+            BitPosition::B6 => 6,
+            BitPosition::B7 => 7,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -53,6 +195,33 @@ impl JumpTest {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RstVector {
+    H00,
+    H08,
+    H10,
+    H18,
+    H20,
+    H28,
+    H30,
+    H38,
+}
+
+impl std::convert::From<RstVector> for u16 {
+    fn from(vector: RstVector) -> u16 {
+        match vector {
+            RstVector::H00 => 0x00,
+            RstVector::H08 => 0x08,
+            RstVector::H10 => 0x10,
+            RstVector::H18 => 0x18,
+            RstVector::H20 => 0x20,
+            RstVector::H28 => 0x28,
+            RstVector::H30 => 0x30,
+            RstVector::H38 => 0x38,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum LoadByteTarget {
     A,
@@ -84,12 +253,160 @@ macro_rules! load_type {
     };
 }
 
+#[derive(Copy, Clone, Debug)]
+pub enum LoadWordTarget {
+    BC,
+    DE,
+    HL,
+    SP,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Indirect {
+    // (BC)
+    BC,
+    // (DE)
+    DE,
+    // (HL) then increment HL
+    HLPlus,
+    // (HL) then decrement HL
+    HLMinus,
+    // (nn) where nn is the next 2 bytes
+    Word,
+    // (0xFF00 + C)
+    LastByte,
+    // (0xFF00 + n) where n is the next byte
+    Byte,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum LoadType {
     Byte(LoadByteTarget, LoadByteSource),
+    // LD rr,nn
+    Word(LoadWordTarget),
+    // LD A,(rr)
+    AFromIndirect(Indirect),
+    // LD (rr),A
+    IndirectFromA(Indirect),
+    // LD SP,HL
+    SPFromHL,
+    // LD HL,SP+n
+    HLFromSPN,
+    // LD (nn),SP
+    IndirectFromSP,
 }
 
 impl Instruction {
+    // Number of M-cycles (4 T-cycles each) the instruction takes to run.
+    // Conditional jumps, calls and returns take longer when the branch is taken
+    // so the flags are needed to know which way they go.
+    pub fn m_cycles(&self, f: FlagsRegister) -> u8 {
+        match self {
+            Instruction::LD(load_type) => match load_type {
+                LoadType::Byte(LoadByteTarget::HLI, LoadByteSource::D8) => 3,
+                LoadType::Byte(LoadByteTarget::HLI, _) => 2,
+                LoadType::Byte(_, LoadByteSource::D8) => 2,
+                LoadType::Byte(_, LoadByteSource::HLI) => 2,
+                LoadType::Byte(_, _) => 1,
+                LoadType::Word(_) => 3,
+                LoadType::AFromIndirect(indirect) | LoadType::IndirectFromA(indirect) => {
+                    match indirect {
+                        Indirect::Word => 4,
+                        Indirect::Byte => 3,
+                        _ => 2,
+                    }
+                }
+                LoadType::SPFromHL => 2,
+                LoadType::HLFromSPN => 3,
+                LoadType::IndirectFromSP => 5,
+            },
+            Instruction::PUSH(_) => 4,
+            Instruction::POP(_) => 3,
+            Instruction::ADD(target)
+            | Instruction::ADC(target)
+            | Instruction::SUB(target)
+            | Instruction::SBC(target)
+            | Instruction::AND(target)
+            | Instruction::OR(target)
+            | Instruction::XOR(target)
+            | Instruction::CP(target) => match target {
+                ArithmeticTarget::D8 | ArithmeticTarget::HLI => 2,
+                _ => 1,
+            },
+            Instruction::INC(target) | Instruction::DEC(target) => match target {
+                IncDecTarget::HLI => 3,
+                _ => 1,
+            },
+            Instruction::INCW(_) | Instruction::DECW(_) => 2,
+            Instruction::ADDHL(_) => 2,
+            Instruction::ADDSP() => 4,
+            Instruction::DAA()
+            | Instruction::CPL()
+            | Instruction::CCF()
+            | Instruction::SCF()
+            | Instruction::RLCA()
+            | Instruction::RLA()
+            | Instruction::RRCA()
+            | Instruction::RRA()
+            | Instruction::DI()
+            | Instruction::EI()
+            | Instruction::NOP()
+            | Instruction::HALT()
+            | Instruction::STOP() => 1,
+            Instruction::RLC(target)
+            | Instruction::RRC(target)
+            | Instruction::RL(target)
+            | Instruction::RR(target)
+            | Instruction::SLA(target)
+            | Instruction::SRA(target)
+            | Instruction::SWAP(target)
+            | Instruction::SRL(target)
+            | Instruction::RES(_, target)
+            | Instruction::SET(_, target) => match target {
+                PrefixTarget::HLI => 4,
+                _ => 2,
+            },
+            // BIT only reads (HL) so it doesn't need the extra write cycle
+            Instruction::BIT(_, target) => match target {
+                PrefixTarget::HLI => 3,
+                _ => 2,
+            },
+            Instruction::JP(test) => {
+                if test.condition_depending_on_flags_reg(f) {
+                    4
+                } else {
+                    3
+                }
+            }
+            Instruction::JR(test) => {
+                if test.condition_depending_on_flags_reg(f) {
+                    3
+                } else {
+                    2
+                }
+            }
+            Instruction::JPHL() => 1,
+            Instruction::CALL(test) => {
+                if test.condition_depending_on_flags_reg(f) {
+                    6
+                } else {
+                    3
+                }
+            }
+            Instruction::RST(_) => 4,
+            // Unconditional RET skips the cycle spent checking the condition
+            Instruction::RET(JumpTest::Always) => 4,
+            Instruction::RET(test) => {
+                if test.condition_depending_on_flags_reg(f) {
+                    5
+                } else {
+                    2
+                }
+            }
+            Instruction::RETI() => 4,
+        }
+    }
+
     pub fn from_byte(byte: u8, prefixed: bool) -> Option<Instruction> {
         if prefixed {
             Self::from_byte_prefixed(byte)
@@ -99,35 +416,55 @@ impl Instruction {
     }
 
     fn from_byte_prefixed(byte: u8) -> Option<Instruction> {
+        let target = PrefixTarget::from_byte(byte);
+        let bit = BitPosition::from_byte(byte);
+
         match byte {
-            // TODO: SWAP n
+            // RLC n
+            0x00..=0x07 => Some(Instruction::RLC(target)),
 
-            // TODO: RLC n
+            // RRC n
+            0x08..=0x0F => Some(Instruction::RRC(target)),
 
-            // TODO: RL n
+            // RL n
+            0x10..=0x17 => Some(Instruction::RL(target)),
 
-            // TODO: RRC n
+            // RR n
+            0x18..=0x1F => Some(Instruction::RR(target)),
 
-            // TODO: RR n
+            // SLA n
+            0x20..=0x27 => Some(Instruction::SLA(target)),
 
-            // TODO: SLA n
+            // SRA n
+            0x28..=0x2F => Some(Instruction::SRA(target)),
 
-            // TODO: SRA n
+            // SWAP n
+            0x30..=0x37 => Some(Instruction::SWAP(target)),
 
-            // TODO: SRL n
+            // SRL n
+            0x38..=0x3F => Some(Instruction::SRL(target)),
 
-            // TODO: BIT b,r
+            // BIT b,r
+            0x40..=0x7F => Some(Instruction::BIT(bit, target)),
 
-            // TODO: SET b,r
+            // RES b,r
+            0x80..=0xBF => Some(Instruction::RES(bit, target)),
 
-            // TODO: RES b,r
-            _ => None,
+            // SET b,r
+            0xC0..=0xFF => Some(Instruction::SET(bit, target)),
         }
     }
 
     fn from_byte_not_prefixed(byte: u8) -> Option<Instruction> {
         match byte {
-            // TODO: LD nn,n
+            // LD nn,n
+            0x3E => Some(Instruction::LD(load_type!(A, D8))),
+            0x06 => Some(Instruction::LD(load_type!(B, D8))),
+            0x0E => Some(Instruction::LD(load_type!(C, D8))),
+            0x16 => Some(Instruction::LD(load_type!(D, D8))),
+            0x1E => Some(Instruction::LD(load_type!(E, D8))),
+            0x26 => Some(Instruction::LD(load_type!(H, D8))),
+            0x2E => Some(Instruction::LD(load_type!(L, D8))),
 
             // LD r1,r2
             0x7F => Some(Instruction::LD(load_type!(A, A))),
@@ -139,6 +476,7 @@ impl Instruction {
             0x7D => Some(Instruction::LD(load_type!(A, L))),
             0x7E => Some(Instruction::LD(load_type!(A, HLI))),
 
+            0x47 => Some(Instruction::LD(load_type!(B, A))),
             0x40 => Some(Instruction::LD(load_type!(B, B))),
             0x41 => Some(Instruction::LD(load_type!(B, C))),
             0x42 => Some(Instruction::LD(load_type!(B, D))),
@@ -147,6 +485,7 @@ impl Instruction {
             0x45 => Some(Instruction::LD(load_type!(B, L))),
             0x46 => Some(Instruction::LD(load_type!(B, HLI))),
 
+            0x4F => Some(Instruction::LD(load_type!(C, A))),
             0x48 => Some(Instruction::LD(load_type!(C, B))),
             0x49 => Some(Instruction::LD(load_type!(C, C))),
             0x4A => Some(Instruction::LD(load_type!(C, D))),
@@ -155,6 +494,7 @@ impl Instruction {
             0x4D => Some(Instruction::LD(load_type!(C, L))),
             0x4E => Some(Instruction::LD(load_type!(C, HLI))),
 
+            0x57 => Some(Instruction::LD(load_type!(D, A))),
             0x50 => Some(Instruction::LD(load_type!(D, B))),
             0x51 => Some(Instruction::LD(load_type!(D, C))),
             0x52 => Some(Instruction::LD(load_type!(D, D))),
@@ -163,6 +503,7 @@ impl Instruction {
             0x55 => Some(Instruction::LD(load_type!(D, L))),
             0x56 => Some(Instruction::LD(load_type!(D, HLI))),
 
+            0x5F => Some(Instruction::LD(load_type!(E, A))),
             0x58 => Some(Instruction::LD(load_type!(E, B))),
             0x59 => Some(Instruction::LD(load_type!(E, C))),
             0x5A => Some(Instruction::LD(load_type!(E, D))),
@@ -171,6 +512,7 @@ impl Instruction {
             0x5D => Some(Instruction::LD(load_type!(E, L))),
             0x5E => Some(Instruction::LD(load_type!(E, HLI))),
 
+            0x67 => Some(Instruction::LD(load_type!(H, A))),
             0x60 => Some(Instruction::LD(load_type!(H, B))),
             0x61 => Some(Instruction::LD(load_type!(H, C))),
             0x62 => Some(Instruction::LD(load_type!(H, D))),
@@ -179,6 +521,7 @@ impl Instruction {
             0x65 => Some(Instruction::LD(load_type!(H, L))),
             0x66 => Some(Instruction::LD(load_type!(H, HLI))),
 
+            0x6F => Some(Instruction::LD(load_type!(L, A))),
             0x68 => Some(Instruction::LD(load_type!(L, B))),
             0x69 => Some(Instruction::LD(load_type!(L, C))),
             0x6A => Some(Instruction::LD(load_type!(L, D))),
@@ -187,42 +530,63 @@ impl Instruction {
             0x6D => Some(Instruction::LD(load_type!(L, L))),
             0x6E => Some(Instruction::LD(load_type!(L, HLI))),
 
+            0x77 => Some(Instruction::LD(load_type!(HLI, A))),
             0x70 => Some(Instruction::LD(load_type!(HLI, B))),
             0x71 => Some(Instruction::LD(load_type!(HLI, C))),
             0x72 => Some(Instruction::LD(load_type!(HLI, D))),
             0x73 => Some(Instruction::LD(load_type!(HLI, E))),
             0x74 => Some(Instruction::LD(load_type!(HLI, H))),
             0x75 => Some(Instruction::LD(load_type!(HLI, L))),
-            // TODO: LD (HL),n 36 12
-            // 0x36 => Some(Instruction::LD(load_type!(HLI, n))),
+            0x36 => Some(Instruction::LD(load_type!(HLI, D8))),
 
-            // TODO: LD A,n
+            // LD A,n
+            0x0A => Some(Instruction::LD(LoadType::AFromIndirect(Indirect::BC))),
+            0x1A => Some(Instruction::LD(LoadType::AFromIndirect(Indirect::DE))),
+            0xFA => Some(Instruction::LD(LoadType::AFromIndirect(Indirect::Word))),
 
-            // TODO: LD n,A
+            // LD n,A
+            0x02 => Some(Instruction::LD(LoadType::IndirectFromA(Indirect::BC))),
+            0x12 => Some(Instruction::LD(LoadType::IndirectFromA(Indirect::DE))),
+            0xEA => Some(Instruction::LD(LoadType::IndirectFromA(Indirect::Word))),
 
-            // TODO: LD A,(C)
+            // LD A,(C)
+            0xF2 => Some(Instruction::LD(LoadType::AFromIndirect(Indirect::LastByte))),
 
-            // TODO: LD (C),A
+            // LD (C),A
+            0xE2 => Some(Instruction::LD(LoadType::IndirectFromA(Indirect::LastByte))),
 
-            // TODO: LDD A,(HL)
+            // LDD A,(HL)
+            0x3A => Some(Instruction::LD(LoadType::AFromIndirect(Indirect::HLMinus))),
 
-            // TODO: LDD (HL),A
+            // LDD (HL),A
+            0x32 => Some(Instruction::LD(LoadType::IndirectFromA(Indirect::HLMinus))),
 
-            // TODO: LDI A,(HL)
+            // LDI A,(HL)
+            0x2A => Some(Instruction::LD(LoadType::AFromIndirect(Indirect::HLPlus))),
 
-            // TODO: LDI (HL),A
+            // LDI (HL),A
+            0x22 => Some(Instruction::LD(LoadType::IndirectFromA(Indirect::HLPlus))),
 
-            // TODO: LDH (n),A
+            // LDH (n),A
+            0xE0 => Some(Instruction::LD(LoadType::IndirectFromA(Indirect::Byte))),
 
-            // TODO: LDH A,(n)
+            // LDH A,(n)
+            0xF0 => Some(Instruction::LD(LoadType::AFromIndirect(Indirect::Byte))),
 
-            // TODO: LD n,nn
+            // LD n,nn
+            0x01 => Some(Instruction::LD(LoadType::Word(LoadWordTarget::BC))),
+            0x11 => Some(Instruction::LD(LoadType::Word(LoadWordTarget::DE))),
+            0x21 => Some(Instruction::LD(LoadType::Word(LoadWordTarget::HL))),
+            0x31 => Some(Instruction::LD(LoadType::Word(LoadWordTarget::SP))),
 
-            // TODO: LDHL SP,HL
+            // LD SP,HL
+            0xF9 => Some(Instruction::LD(LoadType::SPFromHL)),
 
-            // TODO: LDHL SP,n
+            // LDHL SP,n
+            0xF8 => Some(Instruction::LD(LoadType::HLFromSPN)),
 
-            // TODO: LD (nn),SP
+            // LD (nn),SP
+            0x08 => Some(Instruction::LD(LoadType::IndirectFromSP)),
 
             // PUSH nn
             0xF5 => Some(Instruction::PUSH(PushPopTarget::AF)),
@@ -244,44 +608,138 @@ impl Instruction {
             0x83 => Some(Instruction::ADD(ArithmeticTarget::E)),
             0x84 => Some(Instruction::ADD(ArithmeticTarget::H)),
             0x85 => Some(Instruction::ADD(ArithmeticTarget::L)),
-            // TODO: ADD A,(HL) 86 8
-            // 0x86 => Some(Instruction::ADD(ArithmeticTarget::HL)),
-            // TODO: ADD A,# C6 8
-            // 0xC6 => Some(Instruction::ADD(#)),
-
-            // TODO: ADC A,n
-
-            // TODO: SUB n
-
-            // TODO: SBC A,n
-
-            // TODO: AND n
-
-            // TODO: OR n
-
-            // TODO: XOR n
-
-            // TODO: CP n
-
-            // TODO: INC n
-
-            // TODO: DEC n
-
-            // TODO: ADD HL,n
-
-            // TODO: ADD SP,n
-
-            // TODO: INC nn
-
-            // TODO: DEC nn
-
-            // TODO: DAA
-
-            // TODO: CPL
-
-            // TODO: CCF
-
-            // TODO: SCF
+            0x86 => Some(Instruction::ADD(ArithmeticTarget::HLI)),
+            0xC6 => Some(Instruction::ADD(ArithmeticTarget::D8)),
+
+            // ADC A,n
+            0x8F => Some(Instruction::ADC(ArithmeticTarget::A)),
+            0x88 => Some(Instruction::ADC(ArithmeticTarget::B)),
+            0x89 => Some(Instruction::ADC(ArithmeticTarget::C)),
+            0x8A => Some(Instruction::ADC(ArithmeticTarget::D)),
+            0x8B => Some(Instruction::ADC(ArithmeticTarget::E)),
+            0x8C => Some(Instruction::ADC(ArithmeticTarget::H)),
+            0x8D => Some(Instruction::ADC(ArithmeticTarget::L)),
+            0x8E => Some(Instruction::ADC(ArithmeticTarget::HLI)),
+            0xCE => Some(Instruction::ADC(ArithmeticTarget::D8)),
+
+            // SUB n
+            0x97 => Some(Instruction::SUB(ArithmeticTarget::A)),
+            0x90 => Some(Instruction::SUB(ArithmeticTarget::B)),
+            0x91 => Some(Instruction::SUB(ArithmeticTarget::C)),
+            0x92 => Some(Instruction::SUB(ArithmeticTarget::D)),
+            0x93 => Some(Instruction::SUB(ArithmeticTarget::E)),
+            0x94 => Some(Instruction::SUB(ArithmeticTarget::H)),
+            0x95 => Some(Instruction::SUB(ArithmeticTarget::L)),
+            0x96 => Some(Instruction::SUB(ArithmeticTarget::HLI)),
+            0xD6 => Some(Instruction::SUB(ArithmeticTarget::D8)),
+
+            // SBC A,n
+            0x9F => Some(Instruction::SBC(ArithmeticTarget::A)),
+            0x98 => Some(Instruction::SBC(ArithmeticTarget::B)),
+            0x99 => Some(Instruction::SBC(ArithmeticTarget::C)),
+            0x9A => Some(Instruction::SBC(ArithmeticTarget::D)),
+            0x9B => Some(Instruction::SBC(ArithmeticTarget::E)),
+            0x9C => Some(Instruction::SBC(ArithmeticTarget::H)),
+            0x9D => Some(Instruction::SBC(ArithmeticTarget::L)),
+            0x9E => Some(Instruction::SBC(ArithmeticTarget::HLI)),
+            0xDE => Some(Instruction::SBC(ArithmeticTarget::D8)),
+
+            // AND n
+            0xA7 => Some(Instruction::AND(ArithmeticTarget::A)),
+            0xA0 => Some(Instruction::AND(ArithmeticTarget::B)),
+            0xA1 => Some(Instruction::AND(ArithmeticTarget::C)),
+            0xA2 => Some(Instruction::AND(ArithmeticTarget::D)),
+            0xA3 => Some(Instruction::AND(ArithmeticTarget::E)),
+            0xA4 => Some(Instruction::AND(ArithmeticTarget::H)),
+            0xA5 => Some(Instruction::AND(ArithmeticTarget::L)),
+            0xA6 => Some(Instruction::AND(ArithmeticTarget::HLI)),
+            0xE6 => Some(Instruction::AND(ArithmeticTarget::D8)),
+
+            // OR n
+            0xB7 => Some(Instruction::OR(ArithmeticTarget::A)),
+            0xB0 => Some(Instruction::OR(ArithmeticTarget::B)),
+            0xB1 => Some(Instruction::OR(ArithmeticTarget::C)),
+            0xB2 => Some(Instruction::OR(ArithmeticTarget::D)),
+            0xB3 => Some(Instruction::OR(ArithmeticTarget::E)),
+            0xB4 => Some(Instruction::OR(ArithmeticTarget::H)),
+            0xB5 => Some(Instruction::OR(ArithmeticTarget::L)),
+            0xB6 => Some(Instruction::OR(ArithmeticTarget::HLI)),
+            0xF6 => Some(Instruction::OR(ArithmeticTarget::D8)),
+
+            // XOR n
+            0xAF => Some(Instruction::XOR(ArithmeticTarget::A)),
+            0xA8 => Some(Instruction::XOR(ArithmeticTarget::B)),
+            0xA9 => Some(Instruction::XOR(ArithmeticTarget::C)),
+            0xAA => Some(Instruction::XOR(ArithmeticTarget::D)),
+            0xAB => Some(Instruction::XOR(ArithmeticTarget::E)),
+            0xAC => Some(Instruction::XOR(ArithmeticTarget::H)),
+            0xAD => Some(Instruction::XOR(ArithmeticTarget::L)),
+            0xAE => Some(Instruction::XOR(ArithmeticTarget::HLI)),
+            0xEE => Some(Instruction::XOR(ArithmeticTarget::D8)),
+
+            // CP n
+            0xBF => Some(Instruction::CP(ArithmeticTarget::A)),
+            0xB8 => Some(Instruction::CP(ArithmeticTarget::B)),
+            0xB9 => Some(Instruction::CP(ArithmeticTarget::C)),
+            0xBA => Some(Instruction::CP(ArithmeticTarget::D)),
+            0xBB => Some(Instruction::CP(ArithmeticTarget::E)),
+            0xBC => Some(Instruction::CP(ArithmeticTarget::H)),
+            0xBD => Some(Instruction::CP(ArithmeticTarget::L)),
+            0xBE => Some(Instruction::CP(ArithmeticTarget::HLI)),
+            0xFE => Some(Instruction::CP(ArithmeticTarget::D8)),
+
+            // INC n
+            0x3C => Some(Instruction::INC(IncDecTarget::A)),
+            0x04 => Some(Instruction::INC(IncDecTarget::B)),
+            0x0C => Some(Instruction::INC(IncDecTarget::C)),
+            0x14 => Some(Instruction::INC(IncDecTarget::D)),
+            0x1C => Some(Instruction::INC(IncDecTarget::E)),
+            0x24 => Some(Instruction::INC(IncDecTarget::H)),
+            0x2C => Some(Instruction::INC(IncDecTarget::L)),
+            0x34 => Some(Instruction::INC(IncDecTarget::HLI)),
+
+            // DEC n
+            0x3D => Some(Instruction::DEC(IncDecTarget::A)),
+            0x05 => Some(Instruction::DEC(IncDecTarget::B)),
+            0x0D => Some(Instruction::DEC(IncDecTarget::C)),
+            0x15 => Some(Instruction::DEC(IncDecTarget::D)),
+            0x1D => Some(Instruction::DEC(IncDecTarget::E)),
+            0x25 => Some(Instruction::DEC(IncDecTarget::H)),
+            0x2D => Some(Instruction::DEC(IncDecTarget::L)),
+            0x35 => Some(Instruction::DEC(IncDecTarget::HLI)),
+
+            // ADD HL,n
+            0x09 => Some(Instruction::ADDHL(AddHLTarget::BC)),
+            0x19 => Some(Instruction::ADDHL(AddHLTarget::DE)),
+            0x29 => Some(Instruction::ADDHL(AddHLTarget::HL)),
+            0x39 => Some(Instruction::ADDHL(AddHLTarget::SP)),
+
+            // ADD SP,n
+            0xE8 => Some(Instruction::ADDSP()),
+
+            // INC nn
+            0x03 => Some(Instruction::INCW(IncDecWordTarget::BC)),
+            0x13 => Some(Instruction::INCW(IncDecWordTarget::DE)),
+            0x23 => Some(Instruction::INCW(IncDecWordTarget::HL)),
+            0x33 => Some(Instruction::INCW(IncDecWordTarget::SP)),
+
+            // DEC nn
+            0x0B => Some(Instruction::DECW(IncDecWordTarget::BC)),
+            0x1B => Some(Instruction::DECW(IncDecWordTarget::DE)),
+            0x2B => Some(Instruction::DECW(IncDecWordTarget::HL)),
+            0x3B => Some(Instruction::DECW(IncDecWordTarget::SP)),
+
+            // DAA
+            0x27 => Some(Instruction::DAA()),
+
+            // CPL
+            0x2F => Some(Instruction::CPL()),
+
+            // CCF
+            0x3F => Some(Instruction::CCF()),
+
+            // SCF
+            0x37 => Some(Instruction::SCF()),
 
             // NOP
             0x00 => Some(Instruction::NOP()),
@@ -289,19 +747,26 @@ impl Instruction {
             // HALT
             0x76 => Some(Instruction::HALT()),
 
-            // TODO: STOP
+            // STOP
+            0x10 => Some(Instruction::STOP()),
 
-            // TODO: DI
+            // DI
+            0xF3 => Some(Instruction::DI()),
 
-            // TODO: EI
+            // EI
+            0xFB => Some(Instruction::EI()),
 
-            // TODO: RLCA
+            // RLCA
+            0x07 => Some(Instruction::RLCA()),
 
-            // TODO: RLA
+            // RLA
+            0x17 => Some(Instruction::RLA()),
 
-            // TODO: RRCA
+            // RRCA
+            0x0F => Some(Instruction::RRCA()),
 
-            // TODO: RRA
+            // RRA
+            0x1F => Some(Instruction::RRA()),
 
             // JP nn
             0xC3 => Some(Instruction::JP(JumpTest::Always)),
@@ -312,11 +777,17 @@ impl Instruction {
             0xD2 => Some(Instruction::JP(JumpTest::NotCarry)),
             0xDA => Some(Instruction::JP(JumpTest::Carry)),
 
-            // TODO: JP (HL)
+            // JP (HL)
+            0xE9 => Some(Instruction::JPHL()),
 
-            // TODO: JR n
+            // JR n
+            0x18 => Some(Instruction::JR(JumpTest::Always)),
 
-            // TODO: JR cc,n
+            // JR cc,n
+            0x20 => Some(Instruction::JR(JumpTest::NotZero)),
+            0x28 => Some(Instruction::JR(JumpTest::Zero)),
+            0x30 => Some(Instruction::JR(JumpTest::NotCarry)),
+            0x38 => Some(Instruction::JR(JumpTest::Carry)),
 
             // CALL nn
             0xCD => Some(Instruction::CALL(JumpTest::Always)),
@@ -327,7 +798,15 @@ impl Instruction {
             0xD4 => Some(Instruction::CALL(JumpTest::NotCarry)),
             0xDC => Some(Instruction::CALL(JumpTest::Carry)),
 
-            // TODO: RST n
+            // RST n
+            0xC7 => Some(Instruction::RST(RstVector::H00)),
+            0xCF => Some(Instruction::RST(RstVector::H08)),
+            0xD7 => Some(Instruction::RST(RstVector::H10)),
+            0xDF => Some(Instruction::RST(RstVector::H18)),
+            0xE7 => Some(Instruction::RST(RstVector::H20)),
+            0xEF => Some(Instruction::RST(RstVector::H28)),
+            0xF7 => Some(Instruction::RST(RstVector::H30)),
+            0xFF => Some(Instruction::RST(RstVector::H38)),
 
             // RET
             0xC9 => Some(Instruction::RET(JumpTest::Always)),
@@ -338,7 +817,8 @@ impl Instruction {
             0xD0 => Some(Instruction::RET(JumpTest::NotCarry)),
             0xD8 => Some(Instruction::RET(JumpTest::Carry)),
 
-            // TODO: RETI
+            // RETI
+            0xD9 => Some(Instruction::RETI()),
             _ => None,
         }
     }
@@ -346,11 +826,169 @@ impl Instruction {
 
 #[cfg(test)]
 mod tests {
+    use super::super::error::ILLEGAL_OPCODES;
     use super::super::flags_register::FlagsRegister;
     use super::*;
 
     #[test]
-    #[allow(non_snake_case)]
+    fn from_byte_prefixed() {
+        assert!(matches!(
+            Instruction::from_byte(0x00, true),
+            Some(Instruction::RLC(PrefixTarget::B))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0x37, true),
+            Some(Instruction::SWAP(PrefixTarget::A))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0x7E, true),
+            Some(Instruction::BIT(BitPosition::B7, PrefixTarget::HLI))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0x87, true),
+            Some(Instruction::RES(BitPosition::B0, PrefixTarget::A))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0xDB, true),
+            Some(Instruction::SET(BitPosition::B3, PrefixTarget::E))
+        ));
+
+        for byte in 0..=0xFF {
+            assert!(Instruction::from_byte(byte, true).is_some());
+        }
+    }
+
+    #[test]
+    fn from_byte_not_prefixed() {
+        // 0xCB is the prefix and is handled by CPU::step
+        for byte in (0..=0xFF).filter(|&byte| byte != 0xCB) {
+            assert_eq!(
+                Instruction::from_byte(byte, false).is_none(),
+                ILLEGAL_OPCODES.contains(&byte),
+                "0x{:02x}",
+                byte
+            );
+        }
+    }
+
+    #[test]
+    fn from_byte_loads() {
+        assert!(matches!(
+            Instruction::from_byte(0x36, false),
+            Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::HLI,
+                LoadByteSource::D8
+            )))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0x2A, false),
+            Some(Instruction::LD(LoadType::AFromIndirect(Indirect::HLPlus)))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0xE0, false),
+            Some(Instruction::LD(LoadType::IndirectFromA(Indirect::Byte)))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0x31, false),
+            Some(Instruction::LD(LoadType::Word(LoadWordTarget::SP)))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0x08, false),
+            Some(Instruction::LD(LoadType::IndirectFromSP))
+        ));
+    }
+
+    #[test]
+    fn from_byte_control_flow() {
+        assert!(matches!(
+            Instruction::from_byte(0x18, false),
+            Some(Instruction::JR(JumpTest::Always))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0x38, false),
+            Some(Instruction::JR(JumpTest::Carry))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0xE9, false),
+            Some(Instruction::JPHL())
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0xEF, false),
+            Some(Instruction::RST(RstVector::H28))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0xD9, false),
+            Some(Instruction::RETI())
+        ));
+    }
+
+    #[test]
+    fn from_byte_inc_dec() {
+        assert!(matches!(
+            Instruction::from_byte(0x34, false),
+            Some(Instruction::INC(IncDecTarget::HLI))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0x0B, false),
+            Some(Instruction::DECW(IncDecWordTarget::BC))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0x39, false),
+            Some(Instruction::ADDHL(AddHLTarget::SP))
+        ));
+        assert!(matches!(
+            Instruction::from_byte(0xE8, false),
+            Some(Instruction::ADDSP())
+        ));
+    }
+
+    #[test]
+    fn m_cycles() {
+        let f = FlagsRegister::new();
+        let cycles = |byte, prefixed| Instruction::from_byte(byte, prefixed).unwrap().m_cycles(f);
+
+        // NOP
+        assert_eq!(cycles(0x00, false), 1);
+        // LD (HL),d8
+        assert_eq!(cycles(0x36, false), 3);
+        // LD (nn),SP
+        assert_eq!(cycles(0x08, false), 5);
+        // LDH A,(n)
+        assert_eq!(cycles(0xF0, false), 3);
+        // ADD SP,n
+        assert_eq!(cycles(0xE8, false), 4);
+        // PUSH BC
+        assert_eq!(cycles(0xC5, false), 4);
+        // BIT 0,(HL)
+        assert_eq!(cycles(0x46, true), 3);
+        // SET 0,(HL)
+        assert_eq!(cycles(0xC6, true), 4);
+        // RLC B
+        assert_eq!(cycles(0x00, true), 2);
+    }
+
+    #[test]
+    fn m_cycles_branches() {
+        let mut f = FlagsRegister::new();
+
+        // JP NZ, JR NZ, CALL NZ and RET NZ when taken
+        assert_eq!(Instruction::JP(JumpTest::NotZero).m_cycles(f), 4);
+        assert_eq!(Instruction::JR(JumpTest::NotZero).m_cycles(f), 3);
+        assert_eq!(Instruction::CALL(JumpTest::NotZero).m_cycles(f), 6);
+        assert_eq!(Instruction::RET(JumpTest::NotZero).m_cycles(f), 5);
+
+        // and when not taken
+        f.zero = true;
+        assert_eq!(Instruction::JP(JumpTest::NotZero).m_cycles(f), 3);
+        assert_eq!(Instruction::JR(JumpTest::NotZero).m_cycles(f), 2);
+        assert_eq!(Instruction::CALL(JumpTest::NotZero).m_cycles(f), 3);
+        assert_eq!(Instruction::RET(JumpTest::NotZero).m_cycles(f), 2);
+
+        assert_eq!(Instruction::RET(JumpTest::Always).m_cycles(f), 4);
+    }
+
+    #[test]
+    #[allow(non_snake_case, clippy::bool_assert_comparison)]
     fn JumpTest_condition_depending_on_flags_reg() {
         let mut f = FlagsRegister::new();
         f.carry = true;
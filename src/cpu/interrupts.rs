@@ -0,0 +1,68 @@
+pub const INTERRUPT_ENABLE_ADDRESS: u16 = 0xFFFF;
+pub const INTERRUPT_FLAG_ADDRESS: u16 = 0xFF0F;
+
+// Only the lower 5 bits of IE and IF are connected to interrupts
+pub const INTERRUPT_MASK: u8 = 0x1F;
+
+// Ordered from highest to lowest priority
+pub const INTERRUPTS: [Interrupt; 5] = [
+    Interrupt::VBlank,
+    Interrupt::LCDStat,
+    Interrupt::Timer,
+    Interrupt::Serial,
+    Interrupt::Joypad,
+];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interrupt {
+    VBlank,
+    LCDStat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    // The bit of IE and IF that belongs to this interrupt
+    pub fn bit(self) -> u8 {
+        match self {
+            Interrupt::VBlank => 1 << 0,
+            Interrupt::LCDStat => 1 << 1,
+            Interrupt::Timer => 1 << 2,
+            Interrupt::Serial => 1 << 3,
+            Interrupt::Joypad => 1 << 4,
+        }
+    }
+
+    // The address the CPU jumps to when servicing this interrupt
+    pub fn vector(self) -> u16 {
+        match self {
+            Interrupt::VBlank => 0x40,
+            Interrupt::LCDStat => 0x48,
+            Interrupt::Timer => 0x50,
+            Interrupt::Serial => 0x58,
+            Interrupt::Joypad => 0x60,
+        }
+    }
+
+    // Returns the highest priority interrupt set in the given IE & IF bits
+    pub fn highest_priority(pending: u8) -> Option<Interrupt> {
+        INTERRUPTS
+            .iter()
+            .copied()
+            .find(|interrupt| pending & interrupt.bit() != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highest_priority() {
+        assert_eq!(Interrupt::highest_priority(0x00), None);
+        assert_eq!(Interrupt::highest_priority(0x1F), Some(Interrupt::VBlank));
+        assert_eq!(Interrupt::highest_priority(0x14), Some(Interrupt::Timer));
+        assert_eq!(Interrupt::highest_priority(0x10), Some(Interrupt::Joypad));
+    }
+}
@@ -1,22 +1,258 @@
-pub const MEM_SIZE: usize = 0xFFFF;
+use crate::cartridge::Cartridge;
+use crate::ppu::{Renderer, BGP_ADDRESS, LCDC_ADDRESS, LYC_ADDRESS, PPU, WX_ADDRESS};
+
+use super::boot_rom::{BootRom, BOOT_ROM_DISABLE_ADDRESS};
+use super::dma::{Bus, OamDma, DMA_ADDRESS};
+use super::interrupts::{
+    Interrupt, INTERRUPT_ENABLE_ADDRESS, INTERRUPT_FLAG_ADDRESS, INTERRUPT_MASK,
+};
+use super::model::Model;
+use super::timer::{Timer, DIV_ADDRESS, TAC_ADDRESS};
+
+pub const ROM_START: u16 = 0x0000;
+pub const ROM_END: u16 = 0x7FFF;
+
+pub const VRAM_START: u16 = 0x8000;
+pub const VRAM_END: u16 = 0x9FFF;
+
+pub const EXTERNAL_RAM_START: u16 = 0xA000;
+pub const EXTERNAL_RAM_END: u16 = 0xBFFF;
+
+pub const WRAM_START: u16 = 0xC000;
+pub const WRAM_END: u16 = 0xDFFF;
+pub const WRAM_SIZE: usize = (WRAM_END - WRAM_START + 1) as usize;
+
+// Echo RAM mirrors 0xC000-0xDDFF
+pub const ECHO_RAM_START: u16 = 0xE000;
+pub const ECHO_RAM_END: u16 = 0xFDFF;
+
+pub const OAM_START: u16 = 0xFE00;
+pub const OAM_END: u16 = 0xFE9F;
+
+pub const UNUSABLE_START: u16 = 0xFEA0;
+pub const UNUSABLE_END: u16 = 0xFEFF;
+
+pub const IO_START: u16 = 0xFF00;
+pub const IO_END: u16 = 0xFF7F;
+pub const IO_SIZE: usize = (IO_END - IO_START + 1) as usize;
+
+pub const HRAM_START: u16 = 0xFF80;
+pub const HRAM_END: u16 = 0xFFFE;
+pub const HRAM_SIZE: usize = (HRAM_END - HRAM_START + 1) as usize;
+
+// I/O registers that aren't emulated by a component yet and just hold what's
+// written. Everything else in 0xFF00-0xFF7F without a component is unmapped.
+fn io_backed(address: u16) -> bool {
+    match address {
+        // P1, SB and SC
+        0xFF00..=0xFF02 => true,
+        // Sound registers, with two unused gaps
+        0xFF10..=0xFF26 => !matches!(address, 0xFF15 | 0xFF1F),
+        // Wave RAM
+        0xFF30..=0xFF3F => true,
+        _ => false,
+    }
+}
 
 pub struct MemoryBus {
-    memory: [u8; MEM_SIZE],
+    cartridge: Cartridge,
+    // Overlays the start of ROM until it is unmapped through 0xFF50
+    boot_rom: Option<BootRom>,
+    wram: [u8; WRAM_SIZE],
+    io: [u8; IO_SIZE],
+    hram: [u8; HRAM_SIZE],
+    timer: Timer,
+    ppu: PPU,
+    dma: OamDma,
+    // IE (0xFFFF)
+    interrupt_enable: u8,
+    // IF (0xFF0F)
+    interrupt_flag: u8,
 }
 
 impl MemoryBus {
+    #[cfg(test)]
     pub fn new() -> Self {
+        Self::with_renderer(Renderer::Scanline)
+    }
+
+    pub fn with_renderer(renderer: Renderer) -> Self {
         MemoryBus {
-            memory: [0; MEM_SIZE],
+            cartridge: Cartridge::default(),
+            boot_rom: None,
+            wram: [0; WRAM_SIZE],
+            io: [0; IO_SIZE],
+            hram: [0; HRAM_SIZE],
+            timer: Timer::new(),
+            ppu: PPU::with_renderer(renderer),
+            dma: OamDma::new(),
+            interrupt_enable: 0,
+            interrupt_flag: 0,
         }
     }
 
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge) {
+        self.cartridge = cartridge;
+    }
+
+    pub fn load_boot_rom(&mut self, boot_rom: BootRom) {
+        self.boot_rom = Some(boot_rom);
+    }
+
+    pub fn boot_rom_mapped(&self) -> bool {
+        self.boot_rom.is_some()
+    }
+
+    // Loads the I/O registers and divider the boot ROM of model leaves behind,
+    // without starting DMA, restarting the PPU or resetting the timer
+    pub fn load_post_boot_state(&mut self, model: Model) {
+        for (address, byte) in model.post_boot_io() {
+            match address {
+                DMA_ADDRESS => self.dma.set_register(byte),
+                DIV_ADDRESS..=TAC_ADDRESS => self.timer.load_register(address, byte),
+                LCDC_ADDRESS..=LYC_ADDRESS | BGP_ADDRESS..=WX_ADDRESS => {
+                    self.ppu.load_register(address, byte)
+                }
+                _ => self.write_mapped(address, byte),
+            }
+        }
+        self.timer.set_counter(model.post_boot_divider());
+    }
+
+    // Advances the components clocked alongside the CPU
+    pub fn tick(&mut self, m_cycles: u8) {
+        for _ in 0..m_cycles {
+            if self.timer.tick() {
+                self.request_interrupt(Interrupt::Timer);
+            }
+            self.interrupt_flag |= self.ppu.tick();
+            if let Some((source, offset)) = self.dma.tick() {
+                let byte = self.read_mapped(source);
+                self.dma.set_last_byte(byte);
+                self.ppu.write_oam(offset, byte);
+            }
+        }
+    }
+
+    // While OAM DMA runs the CPU can't use OAM or the bus DMA is reading from.
+    // Reads from that bus see the byte being copied instead.
+    fn dma_conflict(&self, address: u16) -> bool {
+        match address {
+            _ if !self.dma.active() => false,
+            OAM_START..=OAM_END => true,
+            ROM_START..=ECHO_RAM_END => self.dma.source_bus() == Some(Bus::of(address)),
+            _ => false,
+        }
+    }
+
+    pub fn ppu(&self) -> &PPU {
+        &self.ppu
+    }
+
+    pub fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    pub fn cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    // Lets tests place program bytes in ROM, which can't be written through the bus
+    #[cfg(test)]
+    pub fn write_rom_byte(&mut self, address: u16, byte: u8) {
+        self.cartridge.write_rom_byte(address - ROM_START, byte);
+    }
+
     pub fn read_byte(&self, address: u16) -> u8 {
-        self.memory[address as usize]
+        match address {
+            OAM_START..=OAM_END if self.dma_conflict(address) => 0xFF,
+            _ if self.dma_conflict(address) => self.dma.last_byte(),
+            _ => self.read_mapped(address),
+        }
     }
 
     pub fn write_byte(&mut self, address: u16, byte: u8) {
-        self.memory[address as usize] = byte;
+        if !self.dma_conflict(address) {
+            self.write_mapped(address, byte);
+        }
+    }
+
+    fn read_mapped(&self, address: u16) -> u8 {
+        match address {
+            ROM_START..=ROM_END => match self.boot_rom.as_ref().and_then(|rom| rom.read(address)) {
+                Some(byte) => byte,
+                None => self.cartridge.read_rom(address - ROM_START),
+            },
+            VRAM_START..=VRAM_END => self.ppu.read_vram(address - VRAM_START),
+            EXTERNAL_RAM_START..=EXTERNAL_RAM_END => {
+                self.cartridge.read_ram(address - EXTERNAL_RAM_START)
+            }
+            WRAM_START..=WRAM_END => self.wram[(address - WRAM_START) as usize],
+            ECHO_RAM_START..=ECHO_RAM_END => self.wram[(address - ECHO_RAM_START) as usize],
+            OAM_START..=OAM_END => self.ppu.read_oam(address - OAM_START),
+            // The DMG reads 0x00 from the unusable area
+            UNUSABLE_START..=UNUSABLE_END => 0x00,
+            // The unused upper 3 bits of IF always read as 1
+            INTERRUPT_FLAG_ADDRESS => self.interrupt_flag | !INTERRUPT_MASK,
+            // The boot ROM register can't be read back
+            BOOT_ROM_DISABLE_ADDRESS => 0xFF,
+            DMA_ADDRESS => self.dma.read_register(),
+            DIV_ADDRESS..=TAC_ADDRESS => self.timer.read_byte(address),
+            LCDC_ADDRESS..=LYC_ADDRESS | BGP_ADDRESS..=WX_ADDRESS => {
+                self.ppu.read_register(address)
+            }
+            IO_START..=IO_END if io_backed(address) => self.io[(address - IO_START) as usize],
+            // Unmapped I/O reads as open bus
+            IO_START..=IO_END => 0xFF,
+            HRAM_START..=HRAM_END => self.hram[(address - HRAM_START) as usize],
+            INTERRUPT_ENABLE_ADDRESS => self.interrupt_enable,
+        }
+    }
+
+    fn write_mapped(&mut self, address: u16, byte: u8) {
+        match address {
+            ROM_START..=ROM_END => self.cartridge.write_rom(address - ROM_START, byte),
+            VRAM_START..=VRAM_END => self.ppu.write_vram(address - VRAM_START, byte),
+            EXTERNAL_RAM_START..=EXTERNAL_RAM_END => {
+                self.cartridge.write_ram(address - EXTERNAL_RAM_START, byte)
+            }
+            WRAM_START..=WRAM_END => self.wram[(address - WRAM_START) as usize] = byte,
+            ECHO_RAM_START..=ECHO_RAM_END => self.wram[(address - ECHO_RAM_START) as usize] = byte,
+            OAM_START..=OAM_END => self.ppu.write_oam(address - OAM_START, byte),
+            // Writes to the unusable area are ignored
+            UNUSABLE_START..=UNUSABLE_END => {}
+            INTERRUPT_FLAG_ADDRESS => self.interrupt_flag = byte & INTERRUPT_MASK,
+            // Once unmapped, the boot ROM stays gone until a reset
+            BOOT_ROM_DISABLE_ADDRESS => {
+                if byte != 0 {
+                    self.boot_rom = None;
+                }
+            }
+            DMA_ADDRESS => self.dma.start(byte),
+            DIV_ADDRESS..=TAC_ADDRESS => self.timer.write_byte(address, byte),
+            LCDC_ADDRESS..=LYC_ADDRESS | BGP_ADDRESS..=WX_ADDRESS => {
+                self.ppu.write_register(address, byte)
+            }
+            IO_START..=IO_END if io_backed(address) => {
+                self.io[(address - IO_START) as usize] = byte
+            }
+            IO_START..=IO_END => {}
+            HRAM_START..=HRAM_END => self.hram[(address - HRAM_START) as usize] = byte,
+            INTERRUPT_ENABLE_ADDRESS => self.interrupt_enable = byte,
+        }
+    }
+
+    pub fn request_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt_flag |= interrupt.bit();
+    }
+
+    pub fn clear_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt_flag &= !interrupt.bit();
+    }
+
+    // Interrupts that are both requested in IF and enabled in IE
+    pub fn pending_interrupts(&self) -> u8 {
+        self.interrupt_enable & self.interrupt_flag & INTERRUPT_MASK
     }
 }
 
@@ -27,14 +263,238 @@ mod tests {
     #[test]
     fn read_byte() {
         let mut mem = MemoryBus::new();
-        mem.memory[0x0000] = 0x49;
-        assert_eq!(mem.read_byte(0x0000), 0x49);
+        mem.wram[0x0000] = 0x49;
+        assert_eq!(mem.read_byte(0xC000), 0x49);
     }
 
     #[test]
     fn write_byte() {
         let mut mem = MemoryBus::new();
-        mem.write_byte(0x0000, 0x49);
-        assert_eq!(mem.memory[0x0000], 0x49);
+        mem.write_byte(0xC000, 0x49);
+        assert_eq!(mem.wram[0x0000], 0x49);
+    }
+
+    #[test]
+    fn rom() {
+        let mut mem = MemoryBus::new();
+        mem.write_rom_byte(0x0001, 0xFE);
+        assert_eq!(mem.read_byte(0x0001), 0xFE);
+
+        // ROM can't be written to without a memory bank controller
+        mem.write_byte(0x0001, 0x00);
+        assert_eq!(mem.read_byte(0x0001), 0xFE);
+
+        // and there is no external RAM either
+        mem.write_byte(0xA000, 0x12);
+        assert_eq!(mem.read_byte(0xA000), 0xFF);
+    }
+
+    #[test]
+    fn boot_rom() {
+        let mut mem = MemoryBus::new();
+        mem.write_rom_byte(0x0000, 0xAA);
+        mem.write_rom_byte(0x0100, 0xBB);
+        mem.load_boot_rom(BootRom::from_bytes(vec![0x31; 0x100]).unwrap());
+
+        assert_eq!(mem.read_byte(0x0000), 0x31);
+        assert_eq!(mem.read_byte(0x0100), 0xBB);
+
+        // Writing zero doesn't unmap it
+        mem.write_byte(BOOT_ROM_DISABLE_ADDRESS, 0x00);
+        assert!(mem.boot_rom_mapped());
+
+        mem.write_byte(BOOT_ROM_DISABLE_ADDRESS, 0x01);
+        assert!(!mem.boot_rom_mapped());
+        assert_eq!(mem.read_byte(0x0000), 0xAA);
+        assert_eq!(mem.read_byte(BOOT_ROM_DISABLE_ADDRESS), 0xFF);
+    }
+
+    #[test]
+    fn timer() {
+        let mut mem = MemoryBus::new();
+        mem.write_byte(TAC_ADDRESS, 0x05);
+        mem.write_byte(0xFF05, 0xFF);
+        assert_eq!(mem.read_byte(TAC_ADDRESS), 0xFD);
+
+        mem.tick(4);
+        assert_eq!(mem.pending_interrupts(), 0x00);
+        mem.write_byte(INTERRUPT_ENABLE_ADDRESS, Interrupt::Timer.bit());
+        mem.tick(1);
+        assert_eq!(mem.pending_interrupts(), Interrupt::Timer.bit());
+
+        mem.tick(60);
+        assert_eq!(mem.read_byte(DIV_ADDRESS), 0x01);
+    }
+
+    #[test]
+    fn ppu() {
+        let mut mem = MemoryBus::new();
+        mem.write_byte(LCDC_ADDRESS, 0x80);
+        mem.write_byte(INTERRUPT_ENABLE_ADDRESS, Interrupt::VBlank.bit());
+
+        // 144 lines of 114 M-cycles each
+        for _ in 0..64 {
+            mem.tick(255);
+        }
+        assert_eq!(mem.read_byte(0xFF44), 143);
+        assert_eq!(mem.pending_interrupts(), 0x00);
+        mem.tick(96);
+        assert_eq!(mem.read_byte(0xFF44), 144);
+        assert_eq!(mem.pending_interrupts(), Interrupt::VBlank.bit());
+    }
+
+    #[test]
+    fn oam_dma() {
+        let mut mem = MemoryBus::new();
+        for i in 0..0xA0 {
+            mem.write_byte(0xC100 + i, i as u8 ^ 0x5A);
+        }
+        mem.write_byte(0xFE00, 0x11);
+        mem.write_byte(0x8000, 0x22);
+        mem.write_byte(0xFF80, 0x33);
+
+        mem.write_byte(DMA_ADDRESS, 0xC1);
+        assert_eq!(mem.read_byte(DMA_ADDRESS), 0xC1);
+        // The CPU keeps the bus for the setup cycle
+        assert_eq!(mem.read_byte(0xFE00), 0x11);
+
+        mem.tick(2);
+        // OAM reads 0xFF and reads from the source bus see the byte being copied
+        assert_eq!(mem.read_byte(0xFE00), 0xFF);
+        assert_eq!(mem.read_byte(0xC000), 0x5A);
+        assert_eq!(mem.read_byte(0x0000), 0x5A);
+        mem.write_byte(0xC000, 0x44);
+        // VRAM is on the other bus and HRAM is always available
+        assert_eq!(mem.read_byte(0x8000), 0x22);
+        assert_eq!(mem.read_byte(0xFF80), 0x33);
+
+        // 160 bytes take 160 M-cycles after the setup cycle
+        mem.tick(158);
+        assert_eq!(mem.read_byte(0xFE00), 0xFF);
+        mem.tick(1);
+        assert_eq!(mem.read_byte(0xC000), 0x00);
+        for i in 0..0xA0 {
+            assert_eq!(mem.read_byte(0xFE00 + i), i as u8 ^ 0x5A);
+        }
+    }
+
+    #[test]
+    fn oam_dma_from_vram() {
+        let mut mem = MemoryBus::new();
+        mem.write_byte(0x8000, 0x22);
+        mem.write_byte(0xC000, 0x44);
+
+        mem.write_byte(DMA_ADDRESS, 0x80);
+        mem.tick(2);
+        // VRAM reads see the byte being copied and writes are dropped
+        assert_eq!(mem.read_byte(0x8001), 0x22);
+        mem.write_byte(0x8000, 0x55);
+        // but WRAM is on the other bus and stays usable
+        assert_eq!(mem.read_byte(0xC000), 0x44);
+        mem.write_byte(0xC000, 0x66);
+        assert_eq!(mem.read_byte(0xC000), 0x66);
+
+        mem.tick(0xA0);
+        assert_eq!(mem.read_byte(0x8000), 0x22);
+        assert_eq!(mem.read_byte(0xFE00), 0x22);
+    }
+
+    #[test]
+    fn post_boot_state() {
+        let mut mem = MemoryBus::new();
+        mem.load_boot_rom(BootRom::from_bytes(vec![0; 0x100]).unwrap());
+        mem.load_post_boot_state(Model::DMG0);
+
+        // DMA reads back without a transfer locking OAM
+        assert_eq!(mem.read_byte(DMA_ADDRESS), 0xFF);
+        assert!(!mem.dma.active());
+        // The PPU is left mid frame instead of being restarted by the LCDC write
+        assert_eq!(mem.read_byte(LCDC_ADDRESS), 0x91);
+        assert_eq!(mem.read_byte(0xFF41), 0x81);
+        assert_eq!(mem.read_byte(0xFF44), 0x91);
+        assert_eq!(mem.read_byte(DIV_ADDRESS), 0x18);
+        assert_eq!(mem.read_byte(TAC_ADDRESS), 0xF8);
+        assert_eq!(mem.read_byte(INTERRUPT_FLAG_ADDRESS), 0xE1);
+        assert!(!mem.boot_rom_mapped());
+    }
+
+    #[test]
+    fn regions() {
+        let mut mem = MemoryBus::new();
+
+        let addresses = [
+            0x8000, 0x9FFF, 0xC000, 0xDFFF, 0xFE00, 0xFE9F, 0xFF80, 0xFFFE,
+        ];
+        for (i, &address) in addresses.iter().enumerate() {
+            mem.write_byte(address, i as u8 + 1);
+        }
+        for (i, &address) in addresses.iter().enumerate() {
+            assert_eq!(mem.read_byte(address), i as u8 + 1, "0x{:04x}", address);
+        }
+        assert_eq!(mem.ppu.read_vram(0x1FFF), 0x02);
+        assert_eq!(mem.hram[0], 0x07);
+    }
+
+    #[test]
+    fn echo_ram() {
+        let mut mem = MemoryBus::new();
+
+        mem.write_byte(0xC123, 0x11);
+        assert_eq!(mem.read_byte(0xE123), 0x11);
+
+        mem.write_byte(0xFDFF, 0x22);
+        assert_eq!(mem.read_byte(0xDDFF), 0x22);
+
+        // 0xDE00-0xDFFF is not mirrored
+        mem.write_byte(0xDE00, 0x33);
+        assert_eq!(mem.read_byte(0xFE00), 0x00);
+    }
+
+    #[test]
+    fn unusable() {
+        let mut mem = MemoryBus::new();
+
+        mem.write_byte(0xFEA0, 0x12);
+        assert_eq!(mem.read_byte(0xFEA0), 0x00);
+        assert_eq!(mem.read_byte(0xFEFF), 0x00);
+    }
+
+    #[test]
+    fn unmapped_io() {
+        let mut mem = MemoryBus::new();
+        for &address in [
+            0xFF03, 0xFF08, 0xFF0E, 0xFF15, 0xFF27, 0xFF4C, 0xFF4D, 0xFF7F,
+        ]
+        .iter()
+        {
+            mem.write_byte(address, 0x12);
+            assert_eq!(mem.read_byte(address), 0xFF, "0x{:04x}", address);
+        }
+
+        // Registers without a component yet still hold what's written
+        for &address in [0xFF01, 0xFF10, 0xFF26, 0xFF30, 0xFF3F].iter() {
+            mem.write_byte(address, 0x12);
+            assert_eq!(mem.read_byte(address), 0x12, "0x{:04x}", address);
+        }
+    }
+
+    #[test]
+    fn interrupt_registers() {
+        let mut mem = MemoryBus::new();
+
+        mem.write_byte(INTERRUPT_ENABLE_ADDRESS, 0x05);
+        assert_eq!(mem.read_byte(INTERRUPT_ENABLE_ADDRESS), 0x05);
+
+        mem.write_byte(INTERRUPT_FLAG_ADDRESS, 0xFF);
+        assert_eq!(mem.read_byte(INTERRUPT_FLAG_ADDRESS), 0xFF);
+        assert_eq!(mem.pending_interrupts(), 0x05);
+
+        mem.clear_interrupt(Interrupt::VBlank);
+        assert_eq!(mem.read_byte(INTERRUPT_FLAG_ADDRESS), 0xFE);
+
+        mem.write_byte(INTERRUPT_FLAG_ADDRESS, 0x00);
+        assert_eq!(mem.read_byte(INTERRUPT_FLAG_ADDRESS), 0xE0);
+        mem.request_interrupt(Interrupt::Timer);
+        assert_eq!(mem.pending_interrupts(), 0x04);
     }
 }
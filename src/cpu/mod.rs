@@ -1,5 +1,20 @@
+mod boot_rom;
+#[allow(clippy::module_inception)]
 mod cpu;
+mod dma;
+mod error;
 mod flags_register;
+// Instructions and operands are named after the Game Boy mnemonics (ADD, HLI, ...)
+#[allow(clippy::upper_case_acronyms)]
 mod instructions;
+mod interrupts;
 mod memorybus;
+mod model;
 mod registers;
+mod timer;
+
+pub use boot_rom::BootRom;
+pub use cpu::CPU;
+pub use error::{BootRomError, CPUError, IllegalOpcodePolicy, RegisterSnapshot};
+pub use interrupts::Interrupt;
+pub use model::Model;
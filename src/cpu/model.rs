@@ -0,0 +1,203 @@
+// The hardware revisions whose boot ROMs leave different state behind
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Model {
+    // The very first DMG boot ROM revision
+    DMG0,
+    DMG,
+    // Game Boy Pocket
+    MGB,
+    SGB,
+    SGB2,
+    CGB,
+    // Game Boy Advance running a CGB cartridge
+    AGB,
+}
+
+// CPU registers as the boot ROM hands them over at 0x0100
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PostBootRegisters {
+    pub af: u16,
+    pub bc: u16,
+    pub de: u16,
+    pub hl: u16,
+}
+
+pub const POST_BOOT_SP: u16 = 0xFFFE;
+pub const POST_BOOT_PC: u16 = 0x0100;
+
+// I/O registers that are the same on every model
+const COMMON_POST_BOOT_IO: [(u16, u8); 37] = [
+    (0xFF00, 0xCF), // P1
+    (0xFF01, 0x00), // SB
+    (0xFF05, 0x00), // TIMA
+    (0xFF06, 0x00), // TMA
+    (0xFF07, 0xF8), // TAC
+    (0xFF0F, 0xE1), // IF
+    (0xFF10, 0x80), // NR10
+    (0xFF11, 0xBF), // NR11
+    (0xFF12, 0xF3), // NR12
+    (0xFF13, 0xFF), // NR13
+    (0xFF14, 0xBF), // NR14
+    (0xFF16, 0x3F), // NR21
+    (0xFF17, 0x00), // NR22
+    (0xFF18, 0xFF), // NR23
+    (0xFF19, 0xBF), // NR24
+    (0xFF1A, 0x7F), // NR30
+    (0xFF1B, 0xFF), // NR31
+    (0xFF1C, 0x9F), // NR32
+    (0xFF1D, 0xFF), // NR33
+    (0xFF1E, 0xBF), // NR34
+    (0xFF20, 0xFF), // NR41
+    (0xFF21, 0x00), // NR42
+    (0xFF22, 0x00), // NR43
+    (0xFF23, 0xBF), // NR44
+    (0xFF24, 0x77), // NR50
+    (0xFF25, 0xF3), // NR51
+    (0xFF40, 0x91), // LCDC
+    (0xFF42, 0x00), // SCY
+    (0xFF43, 0x00), // SCX
+    (0xFF45, 0x00), // LYC
+    (0xFF47, 0xFC), // BGP
+    // OBP0 and OBP1 are left uninitialized by the boot ROM
+    (0xFF48, 0xFF), // OBP0
+    (0xFF49, 0xFF), // OBP1
+    (0xFF4A, 0x00), // WY
+    (0xFF4B, 0x00), // WX
+    (0xFF50, 0xFF), // BOOT
+    (0xFFFF, 0x00), // IE
+];
+
+// Registers that differ between the DMG and CGB families.
+// The CGB only registers read 0xFF on the other models.
+const DMG_POST_BOOT_IO: [(u16, u8); 18] = [
+    (0xFF02, 0x7E), // SC
+    (0xFF46, 0xFF), // DMA
+    (0xFF4D, 0xFF), // KEY1
+    (0xFF4F, 0xFF), // VBK
+    (0xFF51, 0xFF), // HDMA1
+    (0xFF52, 0xFF), // HDMA2
+    (0xFF53, 0xFF), // HDMA3
+    (0xFF54, 0xFF), // HDMA4
+    (0xFF55, 0xFF), // HDMA5
+    (0xFF56, 0xFF), // RP
+    (0xFF68, 0xFF), // BCPS
+    (0xFF69, 0xFF), // BCPD
+    (0xFF6A, 0xFF), // OCPS
+    (0xFF6B, 0xFF), // OCPD
+    (0xFF70, 0xFF), // SVBK
+    (0xFF41, 0x85), // STAT
+    (0xFF44, 0x00), // LY
+    (0xFF26, 0xF1), // NR52
+];
+
+const CGB_POST_BOOT_IO: [(u16, u8); 18] = [
+    (0xFF02, 0x7F), // SC
+    (0xFF46, 0x00), // DMA
+    (0xFF4D, 0x7E), // KEY1
+    (0xFF4F, 0xFE), // VBK
+    (0xFF51, 0xFF), // HDMA1
+    (0xFF52, 0xFF), // HDMA2
+    (0xFF53, 0xFF), // HDMA3
+    (0xFF54, 0xFF), // HDMA4
+    (0xFF55, 0xFF), // HDMA5
+    (0xFF56, 0x3E), // RP
+    (0xFF68, 0xC0), // BCPS
+    (0xFF69, 0xFF), // BCPD
+    (0xFF6A, 0xC1), // OCPS
+    (0xFF6B, 0xFF), // OCPD
+    (0xFF70, 0xF8), // SVBK
+    (0xFF41, 0x85), // STAT
+    (0xFF44, 0x00), // LY
+    (0xFF26, 0xF1), // NR52
+];
+
+impl Model {
+    // The DMG and MGB boot ROMs leave the half carry and carry flags
+    // set unless the header checksum is 0x00
+    pub fn post_boot_registers(&self, header_checksum: u8) -> PostBootRegisters {
+        let dmg_flags = if header_checksum == 0x00 { 0x80 } else { 0xB0 };
+        let (af, bc, de, hl) = match self {
+            Model::DMG0 => (0x0100, 0xFF13, 0x00C1, 0x8403),
+            Model::DMG => (0x0100 | dmg_flags, 0x0013, 0x00D8, 0x014D),
+            Model::MGB => (0xFF00 | dmg_flags, 0x0013, 0x00D8, 0x014D),
+            Model::SGB => (0x0100, 0x0014, 0x0000, 0xC060),
+            Model::SGB2 => (0xFF00, 0x0014, 0x0000, 0xC060),
+            Model::CGB => (0x1180, 0x0000, 0xFF56, 0x000D),
+            Model::AGB => (0x1100, 0x0100, 0xFF56, 0x000D),
+        };
+        PostBootRegisters { af, bc, de, hl }
+    }
+
+    // The I/O registers as (address, value) in the order they should be written
+    pub fn post_boot_io(&self) -> Vec<(u16, u8)> {
+        let mut io = COMMON_POST_BOOT_IO.to_vec();
+        match self {
+            Model::CGB | Model::AGB => io.extend_from_slice(&CGB_POST_BOOT_IO),
+            _ => io.extend_from_slice(&DMG_POST_BOOT_IO),
+        }
+
+        // Model specific differences from the tables above
+        let overrides: &[(u16, u8)] = match self {
+            Model::DMG0 => &[(0xFF41, 0x81), (0xFF44, 0x91)],
+            Model::SGB | Model::SGB2 => &[(0xFF26, 0xF0)],
+            _ => &[],
+        };
+        io.extend_from_slice(overrides);
+        io
+    }
+
+    // The internal 16-bit counter behind DIV (0xFF04)
+    pub fn post_boot_divider(&self) -> u16 {
+        match self {
+            Model::DMG0 => 0x182C,
+            Model::DMG | Model::MGB => 0xABCC,
+            _ => 0x0000,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn post_boot_registers() {
+        assert_eq!(
+            Model::DMG.post_boot_registers(0x4D),
+            PostBootRegisters {
+                af: 0x01B0,
+                bc: 0x0013,
+                de: 0x00D8,
+                hl: 0x014D
+            }
+        );
+        assert_eq!(Model::DMG.post_boot_registers(0x00).af, 0x0180);
+        assert_eq!(Model::MGB.post_boot_registers(0x4D).af, 0xFFB0);
+        assert_eq!(Model::DMG0.post_boot_registers(0x4D).bc, 0xFF13);
+        assert_eq!(Model::SGB2.post_boot_registers(0x4D).af, 0xFF00);
+        assert_eq!(Model::CGB.post_boot_registers(0x4D).af, 0x1180);
+        assert_eq!(Model::AGB.post_boot_registers(0x4D).bc, 0x0100);
+    }
+
+    #[test]
+    fn post_boot_io() {
+        let value = |model: Model, address: u16| {
+            model
+                .post_boot_io()
+                .iter()
+                .rev()
+                .find(|(a, _)| *a == address)
+                .map(|(_, v)| *v)
+        };
+
+        assert_eq!(Model::DMG.post_boot_divider() >> 8, 0xAB);
+        assert_eq!(Model::DMG0.post_boot_divider() >> 8, 0x18);
+        assert_eq!(value(Model::DMG0, 0xFF41), Some(0x81));
+        assert_eq!(value(Model::DMG, 0xFF41), Some(0x85));
+        assert_eq!(value(Model::SGB, 0xFF26), Some(0xF0));
+        assert_eq!(value(Model::DMG, 0xFF02), Some(0x7E));
+        assert_eq!(value(Model::CGB, 0xFF02), Some(0x7F));
+        assert_eq!(value(Model::CGB, 0xFF70), Some(0xF8));
+        assert_eq!(value(Model::DMG, 0xFF70), Some(0xFF));
+    }
+}
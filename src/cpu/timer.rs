@@ -0,0 +1,266 @@
+use super::cpu::T_CYCLES_PER_M_CYCLE;
+
+pub const DIV_ADDRESS: u16 = 0xFF04;
+pub const TIMA_ADDRESS: u16 = 0xFF05;
+pub const TMA_ADDRESS: u16 = 0xFF06;
+pub const TAC_ADDRESS: u16 = 0xFF07;
+
+const TAC_ENABLE: u8 = 0b100;
+const TAC_CLOCK_SELECT: u8 = 0b011;
+// The upper 5 bits of TAC always read as 1
+const TAC_UNUSED: u8 = 0xF8;
+
+pub struct Timer {
+    // DIV is the upper byte of this counter, which goes up every T-cycle
+    counter: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    // TIMA overflowed and reads 0x00 until it is reloaded from TMA on the next M-cycle
+    overflow_pending: bool,
+    // Set for the M-cycle in which TIMA was reloaded from TMA
+    reloading: bool,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Timer {
+            counter: 0,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            overflow_pending: false,
+            reloading: false,
+        }
+    }
+
+    // Sets the internal counter without the side effects of writing DIV
+    pub fn set_counter(&mut self, counter: u16) {
+        self.counter = counter;
+    }
+
+    // Sets a register to a saved value without the edge and reload
+    // side effects of writing it
+    pub fn load_register(&mut self, address: u16, byte: u8) {
+        match address {
+            DIV_ADDRESS => self.counter = (byte as u16) << 8,
+            TIMA_ADDRESS => self.tima = byte,
+            TMA_ADDRESS => self.tma = byte,
+            TAC_ADDRESS => self.tac = byte & !TAC_UNUSED,
+            _ => panic!("0x{:04x} is not a timer register", address),
+        }
+    }
+
+    // Advances the timer by one M-cycle and returns whether the timer interrupt was requested
+    pub fn tick(&mut self) -> bool {
+        let mut interrupt = false;
+        self.reloading = false;
+        if self.overflow_pending {
+            self.overflow_pending = false;
+            self.reloading = true;
+            self.tima = self.tma;
+            interrupt = true;
+        }
+
+        let signal = self.signal();
+        self.counter = self.counter.wrapping_add(T_CYCLES_PER_M_CYCLE as u16);
+        self.falling_edge(signal);
+
+        interrupt
+    }
+
+    pub fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            DIV_ADDRESS => (self.counter >> 8) as u8,
+            TIMA_ADDRESS => self.tima,
+            TMA_ADDRESS => self.tma,
+            TAC_ADDRESS => self.tac | TAC_UNUSED,
+            _ => panic!("0x{:04x} is not a timer register", address),
+        }
+    }
+
+    pub fn write_byte(&mut self, address: u16, byte: u8) {
+        match address {
+            DIV_ADDRESS => {
+                // Resetting the counter can look like a falling edge to TIMA
+                let signal = self.signal();
+                self.counter = 0;
+                self.falling_edge(signal);
+            }
+            TIMA_ADDRESS => {
+                // The reload from TMA wins over writes in the same cycle,
+                // but writing during the delay before it cancels the reload
+                if !self.reloading {
+                    self.tima = byte;
+                    self.overflow_pending = false;
+                }
+            }
+            TMA_ADDRESS => {
+                self.tma = byte;
+                if self.reloading {
+                    self.tima = byte;
+                }
+            }
+            TAC_ADDRESS => {
+                // Disabling the timer or switching clocks can also cause a falling edge
+                let signal = self.signal();
+                self.tac = byte & !TAC_UNUSED;
+                self.falling_edge(signal);
+            }
+            _ => panic!("0x{:04x} is not a timer register", address),
+        }
+    }
+
+    // TIMA is incremented on the falling edge of the selected
+    // counter bit ANDed with the timer enable bit
+    fn signal(&self) -> bool {
+        let bit = match self.tac & TAC_CLOCK_SELECT {
+            0b00 => 9, // 4096 Hz
+            0b01 => 3, // 262144 Hz
+            0b10 => 5, // 65536 Hz
+            _ => 7,    // 16384 Hz
+        };
+        self.tac & TAC_ENABLE != 0 && (self.counter >> bit) & 1 == 1
+    }
+
+    fn falling_edge(&mut self, previous_signal: bool) {
+        if previous_signal && !self.signal() {
+            self.increment_tima();
+        }
+    }
+
+    fn increment_tima(&mut self) {
+        let (tima, overflowed) = self.tima.overflowing_add(1);
+        self.tima = tima;
+        if overflowed {
+            self.overflow_pending = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(timer: &mut Timer, m_cycles: u32) -> bool {
+        let mut interrupt = false;
+        for _ in 0..m_cycles {
+            interrupt |= timer.tick();
+        }
+        interrupt
+    }
+
+    #[test]
+    fn div() {
+        let mut timer = Timer::new();
+        tick(&mut timer, 64);
+        assert_eq!(timer.read_byte(DIV_ADDRESS), 0x01);
+
+        // Any write resets the whole counter
+        tick(&mut timer, 63);
+        timer.write_byte(DIV_ADDRESS, 0xAB);
+        assert_eq!(timer.read_byte(DIV_ADDRESS), 0x00);
+        tick(&mut timer, 1);
+        assert_eq!(timer.read_byte(DIV_ADDRESS), 0x00);
+    }
+
+    #[test]
+    fn tima_frequencies() {
+        for &(tac, m_cycles) in [(0b100, 256), (0b101, 4), (0b110, 16), (0b111, 64)].iter() {
+            let mut timer = Timer::new();
+            timer.write_byte(TAC_ADDRESS, tac);
+            tick(&mut timer, m_cycles - 1);
+            assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x00, "TAC 0b{:03b}", tac);
+            tick(&mut timer, 1);
+            assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x01, "TAC 0b{:03b}", tac);
+        }
+
+        // Nothing happens while the timer is disabled
+        let mut timer = Timer::new();
+        timer.write_byte(TAC_ADDRESS, 0b001);
+        tick(&mut timer, 100);
+        assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x00);
+        assert_eq!(timer.read_byte(TAC_ADDRESS), 0xF9);
+    }
+
+    #[test]
+    fn overflow() {
+        let mut timer = Timer::new();
+        timer.write_byte(TMA_ADDRESS, 0x42);
+        timer.write_byte(TIMA_ADDRESS, 0xFF);
+        timer.write_byte(TAC_ADDRESS, 0b101);
+
+        assert!(!tick(&mut timer, 4));
+        // TIMA reads 0x00 for one M-cycle before the reload and interrupt
+        assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x00);
+        assert!(timer.tick());
+        assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x42);
+    }
+
+    #[test]
+    fn overflow_cancelled_by_tima_write() {
+        let mut timer = Timer::new();
+        timer.write_byte(TMA_ADDRESS, 0x42);
+        timer.write_byte(TIMA_ADDRESS, 0xFF);
+        timer.write_byte(TAC_ADDRESS, 0b101);
+        tick(&mut timer, 4);
+
+        timer.write_byte(TIMA_ADDRESS, 0x10);
+        assert!(!timer.tick());
+        assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x10);
+    }
+
+    #[test]
+    fn writes_during_reload() {
+        let mut timer = Timer::new();
+        timer.write_byte(TMA_ADDRESS, 0x42);
+        timer.write_byte(TIMA_ADDRESS, 0xFF);
+        timer.write_byte(TAC_ADDRESS, 0b101);
+        tick(&mut timer, 5);
+
+        // TIMA writes are ignored in the reload cycle
+        timer.write_byte(TIMA_ADDRESS, 0x10);
+        assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x42);
+
+        // but TMA writes go straight through to TIMA
+        timer.write_byte(TMA_ADDRESS, 0x20);
+        assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x20);
+
+        timer.tick();
+        timer.write_byte(TMA_ADDRESS, 0x30);
+        assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x20);
+    }
+
+    #[test]
+    fn div_write_glitch() {
+        let mut timer = Timer::new();
+        timer.write_byte(TAC_ADDRESS, 0b100);
+        // Bit 9 of the counter is set
+        tick(&mut timer, 128);
+        assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x00);
+
+        timer.write_byte(DIV_ADDRESS, 0x00);
+        assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x01);
+
+        // No edge when the selected bit is clear
+        tick(&mut timer, 127);
+        timer.write_byte(DIV_ADDRESS, 0x00);
+        assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x01);
+    }
+
+    #[test]
+    fn tac_write_glitch() {
+        let mut timer = Timer::new();
+        timer.write_byte(TAC_ADDRESS, 0b100);
+        tick(&mut timer, 128);
+
+        // Disabling the timer while the selected bit is set
+        timer.write_byte(TAC_ADDRESS, 0b000);
+        assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x01);
+
+        // Switching from a set bit (9) to a clear one (3)
+        timer.write_byte(TAC_ADDRESS, 0b100);
+        timer.write_byte(TAC_ADDRESS, 0b101);
+        assert_eq!(timer.read_byte(TIMA_ADDRESS), 0x02);
+    }
+}
@@ -1,4 +1,6 @@
+pub mod cartridge;
 pub mod cpu;
+pub mod ppu;
 
 #[cfg(test)]
 mod tests {
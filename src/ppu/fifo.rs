@@ -0,0 +1,449 @@
+use std::collections::VecDeque;
+
+use super::scanline::WINDOW_X_OFFSET;
+use super::sprites::{ObjPixel, Sprite};
+use super::{
+    LCDC_BG_TILE_MAP, LCDC_BG_WINDOW_ENABLE, LCDC_OBJ_ENABLE, LCDC_WINDOW_ENABLE,
+    LCDC_WINDOW_TILE_MAP, PPU, SCREEN_WIDTH, TILE_MAP_0, TILE_MAP_1, TILE_MAP_WIDTH,
+};
+
+// The fetcher's first tile is fetched and thrown away at the start of every line
+const STARTUP_DOTS: u8 = 6;
+// Getting the tile number, the low byte and the high byte take 2 dots each
+const FETCH_DOTS: u8 = 6;
+const SPRITE_FETCH_DOTS: u8 = 6;
+const TILE_WIDTH: usize = 8;
+
+// State of the pixel FIFO renderer during pixel transfer
+pub(super) struct PixelFifo {
+    background: VecDeque<u8>,
+    objects: VecDeque<ObjPixel>,
+    startup_dots: u8,
+    // Dots into the current background fetch
+    fetcher_dots: u8,
+    // Tile column the background fetcher is on, relative to the start of the line or window
+    fetcher_x: u8,
+    tile_address: usize,
+    tile_row: u8,
+    // A fetched row waiting for the background FIFO to empty
+    fetched: Option<[u8; TILE_WIDTH]>,
+    // Pixels still to throw away for SCX fine scrolling or a window left of the LCD
+    discard: u8,
+    // Pixels pushed to the LCD on this line
+    lcd_x: u8,
+    // Sprites on this line that haven't been fetched yet, in DMG priority order
+    sprites: VecDeque<Sprite>,
+    // The sprite being fetched and the dots left on it, including
+    // the wait for the background fetcher
+    sprite_fetch: Option<(Sprite, u8)>,
+    // The background or window tile (window, index) that a sprite last waited on
+    waited_tile: Option<(bool, i16)>,
+    window: bool,
+}
+
+impl PixelFifo {
+    pub(super) fn new() -> Self {
+        PixelFifo {
+            background: VecDeque::with_capacity(2 * TILE_WIDTH),
+            objects: VecDeque::with_capacity(TILE_WIDTH),
+            startup_dots: 0,
+            fetcher_dots: 0,
+            fetcher_x: 0,
+            tile_address: 0,
+            tile_row: 0,
+            fetched: None,
+            discard: 0,
+            lcd_x: 0,
+            sprites: VecDeque::new(),
+            sprite_fetch: None,
+            waited_tile: None,
+            window: false,
+        }
+    }
+
+    fn reset_fetcher(&mut self) {
+        self.background.clear();
+        self.fetcher_dots = 0;
+        self.fetcher_x = 0;
+        self.fetched = None;
+    }
+}
+
+impl PPU {
+    pub(super) fn start_pixel_fifo(&mut self) {
+        self.check_window_trigger();
+
+        let mut sprites = if self.lcdc & LCDC_OBJ_ENABLE != 0 {
+            self.scan_oam()
+        } else {
+            Vec::new()
+        };
+        sprites.sort_by_key(|sprite| sprite.x);
+
+        let fifo = &mut self.fifo;
+        fifo.reset_fetcher();
+        fifo.objects.clear();
+        fifo.startup_dots = STARTUP_DOTS;
+        fifo.discard = self.scx % TILE_WIDTH as u8;
+        fifo.lcd_x = 0;
+        fifo.sprites = sprites.into();
+        fifo.sprite_fetch = None;
+        fifo.waited_tile = None;
+        fifo.window = false;
+    }
+
+    // Runs the FIFO for one dot and returns whether the line is finished
+    pub(super) fn step_pixel_fifo(&mut self) -> bool {
+        if self.fifo.startup_dots > 0 {
+            self.fifo.startup_dots -= 1;
+            return false;
+        }
+
+        // Reaching WX restarts the fetcher on the window
+        if !self.fifo.window
+            && self.window_visible()
+            && self.fifo.lcd_x + WINDOW_X_OFFSET >= self.wx
+        {
+            self.fifo.window = true;
+            self.fifo.reset_fetcher();
+            // With WX below 7 the window starts left of the LCD, so its first
+            // columns are thrown away like SCX fine scrolling does
+            self.fifo.discard = WINDOW_X_OFFSET.saturating_sub(self.wx);
+        }
+
+        // Pixels stop shifting out until the sprite is fetched
+        if let Some((sprite, dots)) = self.fifo.sprite_fetch {
+            // The background fetcher keeps going while the sprite waits on it,
+            // then the sprite fetch takes it over
+            if dots > SPRITE_FETCH_DOTS {
+                self.step_fetcher();
+            }
+            if dots > 1 {
+                self.fifo.sprite_fetch = Some((sprite, dots - 1));
+            } else {
+                self.merge_sprite(&sprite);
+                self.fifo.sprite_fetch = None;
+            }
+            return false;
+        }
+
+        self.step_fetcher();
+        if self.start_sprite_fetch() {
+            return false;
+        }
+        self.shift_pixel()
+    }
+
+    // Starts fetching the next sprite once the pixel about to be shifted out
+    // is its leftmost one, counting this dot as the first of the fetch
+    fn start_sprite_fetch(&mut self) -> bool {
+        if self.lcdc & LCDC_OBJ_ENABLE == 0 || self.fifo.background.is_empty() {
+            return false;
+        }
+        let lcd_x = self.fifo.lcd_x as u16 + 8;
+        let sprite = match self.fifo.sprites.front() {
+            Some(&sprite) if sprite.x as u16 <= lcd_x => sprite,
+            _ => return false,
+        };
+        self.fifo.sprites.pop_front();
+
+        let dots = self.sprite_wait(&sprite) + SPRITE_FETCH_DOTS - 1;
+        self.fifo.sprite_fetch = Some((sprite, dots));
+        true
+    }
+
+    fn step_fetcher(&mut self) {
+        if let Some(row) = self.fifo.fetched {
+            if self.fifo.background.is_empty() {
+                self.fifo.background.extend(row.iter().copied());
+                self.fifo.fetched = None;
+                self.fifo.fetcher_dots = 0;
+                self.fifo.fetcher_x = self.fifo.fetcher_x.wrapping_add(1);
+            }
+            return;
+        }
+
+        self.fifo.fetcher_dots += 1;
+        match self.fifo.fetcher_dots {
+            // The tile number, with SCX, SCY and LCDC read as they are now
+            2 => {
+                let (map_bit, x, y) = if self.fifo.window {
+                    let x = self.fifo.fetcher_x as usize * TILE_WIDTH;
+                    (LCDC_WINDOW_TILE_MAP, x as u8, self.window_line)
+                } else {
+                    let x = self.scx as usize + self.fifo.fetcher_x as usize * TILE_WIDTH;
+                    (LCDC_BG_TILE_MAP, x as u8, self.ly.wrapping_add(self.scy))
+                };
+                let map = if self.lcdc & map_bit != 0 {
+                    TILE_MAP_1
+                } else {
+                    TILE_MAP_0
+                };
+                let index = map + (y as usize / 8) * TILE_MAP_WIDTH + x as usize / 8;
+                self.fifo.tile_address = self.bg_tile_address(self.vram[index]);
+                self.fifo.tile_row = y % 8;
+            }
+            FETCH_DOTS => {
+                let mut row = [0; TILE_WIDTH];
+                for (x, color) in row.iter_mut().enumerate() {
+                    *color = self.tile_color(self.fifo.tile_address, x as u8, self.fifo.tile_row);
+                }
+                self.fifo.fetched = Some(row);
+            }
+            _ => {}
+        }
+    }
+
+    // Pushes one pixel to the LCD and returns whether the line is finished
+    fn shift_pixel(&mut self) -> bool {
+        let bg_color = match self.fifo.background.pop_front() {
+            Some(color) => color,
+            None => return false,
+        };
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+            return false;
+        }
+
+        let obj = self
+            .fifo
+            .objects
+            .pop_front()
+            .filter(|pixel| pixel.color != 0);
+        let bg_color = if self.lcdc & LCDC_BG_WINDOW_ENABLE != 0 {
+            bg_color
+        } else {
+            0
+        };
+        let index = self.ly as usize * SCREEN_WIDTH as usize + self.fifo.lcd_x as usize;
+        self.framebuffer[index] = self.mix(bg_color, obj);
+
+        self.fifo.lcd_x += 1;
+        if self.fifo.lcd_x < SCREEN_WIDTH {
+            return false;
+        }
+        if self.fifo.window {
+            self.window_line += 1;
+        }
+        true
+    }
+
+    // Sprite pixels only replace transparent ones already in the FIFO,
+    // which keeps earlier (higher priority) sprites on top
+    fn merge_sprite(&mut self, sprite: &Sprite) {
+        while self.fifo.objects.len() < TILE_WIDTH {
+            self.fifo.objects.push_back(ObjPixel::TRANSPARENT);
+        }
+
+        // Columns already left of the LCD are skipped
+        let skip = self.fifo.lcd_x as usize + 8 - sprite.x as usize;
+        for column in skip..TILE_WIDTH {
+            let x = self.fifo.lcd_x + (column - skip) as u8;
+            let pixel = ObjPixel::new(sprite, self.sprite_color(sprite, x));
+            let slot = &mut self.fifo.objects[column - skip];
+            if slot.color == 0 {
+                *slot = pixel;
+            }
+        }
+    }
+
+    // The dots a sprite waits for the background fetch of the tile its leftmost
+    // pixel is on: the pixels of that tile right of it minus 2. Only the first
+    // sprite on a tile waits, and a sprite at X 0 always waits the longest.
+    fn sprite_wait(&mut self, sprite: &Sprite) -> u8 {
+        let left = sprite.x as i16 - 8;
+        let position = if self.fifo.window {
+            left + WINDOW_X_OFFSET as i16 - self.wx as i16
+        } else {
+            left + self.scx as i16
+        };
+        let tile = (self.fifo.window, position.div_euclid(TILE_WIDTH as i16));
+        if self.fifo.waited_tile == Some(tile) {
+            return 0;
+        }
+        self.fifo.waited_tile = Some(tile);
+
+        let column = if sprite.x == 0 {
+            0
+        } else {
+            position.rem_euclid(TILE_WIDTH as i16)
+        };
+        (TILE_WIDTH as i16 - 1 - column - 2).max(0) as u8
+    }
+
+    fn window_visible(&self) -> bool {
+        self.lcdc & LCDC_WINDOW_ENABLE != 0
+            && self.window_triggered
+            && self.wx < SCREEN_WIDTH + WINDOW_X_OFFSET
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::sprites::tests::write_sprite;
+    use super::super::{
+        Mode, Renderer, BGP_ADDRESS, LCDC_ADDRESS, LCDC_LCD_ENABLE, LCDC_TILE_DATA, OBP0_ADDRESS,
+        SCX_ADDRESS,
+    };
+    use super::*;
+
+    const LCDC: u8 = LCDC_LCD_ENABLE | LCDC_BG_WINDOW_ENABLE | LCDC_TILE_DATA | LCDC_OBJ_ENABLE;
+
+    // Fills VRAM and OAM with a busy pattern of tiles, a window and sprites
+    fn busy_ppu(renderer: Renderer) -> PPU {
+        let mut ppu = PPU::with_renderer(renderer);
+        for (i, byte) in ppu.vram.iter_mut().enumerate() {
+            *byte = (i.wrapping_mul(37) ^ (i >> 3)) as u8;
+        }
+        let sprites: [[u8; 4]; 6] = [
+            [20, 4, 3, 0x00],
+            [24, 10, 5, 0x20],
+            [30, 10, 6, 0x40],
+            [40, 90, 7, 0x80],
+            [40, 93, 8, 0x10],
+            [60, 167, 9, 0x60],
+        ];
+        for (i, &[y, x, tile, attributes]) in sprites.iter().enumerate() {
+            write_sprite(&mut ppu, i, y, x, tile, attributes);
+        }
+        ppu.write_register(LCDC_ADDRESS, LCDC | LCDC_WINDOW_ENABLE);
+        ppu.write_register(BGP_ADDRESS, 0b11_10_01_00);
+        ppu.write_register(OBP0_ADDRESS, 0b00_01_10_11);
+        ppu.scx = 13;
+        ppu.scy = 200;
+        ppu.wy = 70;
+        ppu
+    }
+
+    fn run_frame(ppu: &mut PPU) {
+        let frames = ppu.frames();
+        while ppu.frames() == frames {
+            ppu.tick();
+        }
+    }
+
+    // Dots from the start of pixel transfer to HBlank on line 0
+    fn mode_3_length(ppu: &mut PPU) -> u16 {
+        while ppu.mode() != Mode::PixelTransfer {
+            ppu.tick_dot();
+        }
+        let start = ppu.dot;
+        while ppu.mode() == Mode::PixelTransfer {
+            ppu.tick_dot();
+        }
+        ppu.dot - start
+    }
+
+    fn fifo_ppu(lcdc: u8) -> PPU {
+        let mut ppu = PPU::with_renderer(Renderer::Fifo);
+        ppu.write_register(LCDC_ADDRESS, LCDC | lcdc);
+        ppu
+    }
+
+    #[test]
+    fn matches_scanline() {
+        // The window starting part way along the line, and left of the LCD
+        for &wx in [100, 7, 3, 0].iter() {
+            let mut scanline = busy_ppu(Renderer::Scanline);
+            let mut fifo = busy_ppu(Renderer::Fifo);
+            scanline.wx = wx;
+            fifo.wx = wx;
+            run_frame(&mut scanline);
+            run_frame(&mut fifo);
+
+            let scanline = scanline.framebuffer();
+            let fifo = fifo.framebuffer();
+            for (i, (a, b)) in scanline.iter().zip(fifo.iter()).enumerate() {
+                assert_eq!(a, b, "WX {} x {} y {}", wx, i % 160, i / 160);
+            }
+        }
+    }
+
+    #[test]
+    fn mode_3_timing() {
+        assert_eq!(mode_3_length(&mut fifo_ppu(0)), 172);
+
+        // Fine scrolling throws pixels away
+        let mut ppu = fifo_ppu(0);
+        ppu.write_register(SCX_ADDRESS, 3);
+        assert_eq!(mode_3_length(&mut ppu), 175);
+
+        // The window restarts the fetcher
+        let mut ppu = fifo_ppu(LCDC_WINDOW_ENABLE);
+        ppu.wx = 7 + 80;
+        assert_eq!(mode_3_length(&mut ppu), 178);
+    }
+
+    #[test]
+    fn sprite_penalty() {
+        let penalty = |scx: u8, sprites: &[u8]| {
+            let mut ppu = fifo_ppu(0);
+            ppu.write_register(SCX_ADDRESS, scx);
+            for (i, &x) in sprites.iter().enumerate() {
+                write_sprite(&mut ppu, i, 16, x, 0, 0);
+            }
+            mode_3_length(&mut ppu) - 172 - scx as u16 % 8
+        };
+
+        // Penalties for a sprite at X 0 to 16, for SCX 0 to 7
+        let table: [[u16; 17]; 8] = [
+            [11, 10, 9, 8, 7, 6, 6, 6, 11, 10, 9, 8, 7, 6, 6, 6, 11],
+            [11, 9, 8, 7, 6, 6, 6, 11, 10, 9, 8, 7, 6, 6, 6, 11, 10],
+            [11, 8, 7, 6, 6, 6, 11, 10, 9, 8, 7, 6, 6, 6, 11, 10, 9],
+            [11, 7, 6, 6, 6, 11, 10, 9, 8, 7, 6, 6, 6, 11, 10, 9, 8],
+            [11, 6, 6, 6, 11, 10, 9, 8, 7, 6, 6, 6, 11, 10, 9, 8, 7],
+            [11, 6, 6, 11, 10, 9, 8, 7, 6, 6, 6, 11, 10, 9, 8, 7, 6],
+            [11, 6, 11, 10, 9, 8, 7, 6, 6, 6, 11, 10, 9, 8, 7, 6, 6],
+            [11, 11, 10, 9, 8, 7, 6, 6, 6, 11, 10, 9, 8, 7, 6, 6, 6],
+        ];
+        for (scx, row) in table.iter().enumerate() {
+            for (x, &expected) in row.iter().enumerate() {
+                assert_eq!(
+                    penalty(scx as u8, &[x as u8]),
+                    expected,
+                    "SCX {} X {}",
+                    scx,
+                    x
+                );
+            }
+        }
+
+        // Only the first sprite on a tile waits for the background fetcher
+        assert_eq!(penalty(0, &[8, 8]), 11 + 6);
+        assert_eq!(penalty(0, &[8, 12]), 11 + 6);
+        assert_eq!(penalty(0, &[8, 16]), 11 + 11);
+
+        // Disabled sprites cost nothing
+        let mut ppu = fifo_ppu(0);
+        ppu.lcdc &= !LCDC_OBJ_ENABLE;
+        write_sprite(&mut ppu, 0, 16, 8, 0, 0);
+        assert_eq!(mode_3_length(&mut ppu), 172);
+    }
+
+    #[test]
+    fn mid_line_palette_change() {
+        let mut ppu = fifo_ppu(0);
+        // Every background pixel is color 3
+        for byte in ppu.vram[..16].iter_mut() {
+            *byte = 0xFF;
+        }
+        ppu.write_register(BGP_ADDRESS, 0b11_00_00_00);
+
+        while ppu.mode() != Mode::PixelTransfer {
+            ppu.tick_dot();
+        }
+        for _ in 0..92 {
+            ppu.tick_dot();
+        }
+        ppu.write_register(BGP_ADDRESS, 0b01_00_00_00);
+        while ppu.mode() == Mode::PixelTransfer {
+            ppu.tick_dot();
+        }
+
+        let line = &ppu.framebuffer()[..160];
+        assert_eq!(line[0], 3);
+        assert_eq!(line[159], 1);
+        let changed = line.iter().position(|&shade| shade == 1).unwrap();
+        assert!(line[changed..].iter().all(|&shade| shade == 1));
+    }
+}
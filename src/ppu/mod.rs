@@ -0,0 +1,484 @@
+use crate::cpu::Interrupt;
+
+mod fifo;
+mod scanline;
+mod sprites;
+
+use fifo::PixelFifo;
+
+pub const VRAM_SIZE: usize = 0x2000;
+pub const OAM_SIZE: usize = 0xA0;
+
+pub const LCDC_ADDRESS: u16 = 0xFF40;
+pub const STAT_ADDRESS: u16 = 0xFF41;
+pub const SCY_ADDRESS: u16 = 0xFF42;
+pub const SCX_ADDRESS: u16 = 0xFF43;
+pub const LY_ADDRESS: u16 = 0xFF44;
+pub const LYC_ADDRESS: u16 = 0xFF45;
+pub const BGP_ADDRESS: u16 = 0xFF47;
+pub const OBP0_ADDRESS: u16 = 0xFF48;
+pub const OBP1_ADDRESS: u16 = 0xFF49;
+pub const WY_ADDRESS: u16 = 0xFF4A;
+pub const WX_ADDRESS: u16 = 0xFF4B;
+
+pub const SCREEN_WIDTH: u8 = 160;
+pub const SCREEN_HEIGHT: u8 = 144;
+// One shade (0 = white to 3 = black) per pixel, row by row
+pub const FRAMEBUFFER_SIZE: usize = SCREEN_WIDTH as usize * SCREEN_HEIGHT as usize;
+
+pub const DOTS_PER_M_CYCLE: u8 = 4;
+pub const DOTS_PER_LINE: u16 = 456;
+pub const LINES_PER_FRAME: u8 = 154;
+const OAM_SCAN_DOTS: u16 = 80;
+const PIXEL_TRANSFER_DOTS: u16 = 172;
+
+const LCDC_BG_WINDOW_ENABLE: u8 = 1 << 0;
+const LCDC_OBJ_ENABLE: u8 = 1 << 1;
+// Set for 8x16 sprites
+const LCDC_OBJ_SIZE: u8 = 1 << 2;
+const LCDC_BG_TILE_MAP: u8 = 1 << 3;
+// Set for 0x8000 unsigned addressing, clear for 0x8800 signed addressing
+const LCDC_TILE_DATA: u8 = 1 << 4;
+const LCDC_WINDOW_ENABLE: u8 = 1 << 5;
+const LCDC_WINDOW_TILE_MAP: u8 = 1 << 6;
+const LCDC_LCD_ENABLE: u8 = 1 << 7;
+
+// VRAM offsets of the tile maps and the base of signed tile addressing
+const TILE_MAP_0: usize = 0x1800;
+const TILE_MAP_1: usize = 0x1C00;
+const SIGNED_TILE_DATA: usize = 0x1000;
+const TILE_SIZE: usize = 16;
+const TILE_MAP_WIDTH: usize = 32;
+
+// The interrupt select bits are the only writable part of STAT
+const STAT_HBLANK_INTERRUPT: u8 = 1 << 3;
+const STAT_VBLANK_INTERRUPT: u8 = 1 << 4;
+const STAT_OAM_SCAN_INTERRUPT: u8 = 1 << 5;
+const STAT_LYC_INTERRUPT: u8 = 1 << 6;
+const STAT_WRITABLE: u8 = 0b0111_1000;
+const STAT_LYC_EQUAL: u8 = 1 << 2;
+const STAT_UNUSED: u8 = 1 << 7;
+
+// How pixel transfer turns VRAM into pixels
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Renderer {
+    // Draws each line in one go with a fixed length mode 3. Fast, but
+    // changes to registers in the middle of a line aren't seen.
+    Scanline,
+    // Emulates the background fetcher and pixel FIFO dot by dot
+    Fifo,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mode {
+    HBlank,
+    VBlank,
+    OAMScan,
+    PixelTransfer,
+}
+
+impl std::convert::From<Mode> for u8 {
+    fn from(mode: Mode) -> u8 {
+        match mode {
+            Mode::HBlank => 0,
+            Mode::VBlank => 1,
+            Mode::OAMScan => 2,
+            Mode::PixelTransfer => 3,
+        }
+    }
+}
+
+pub struct PPU {
+    vram: [u8; VRAM_SIZE],
+    oam: [u8; OAM_SIZE],
+    lcdc: u8,
+    // Only the interrupt select bits, the rest of STAT is derived
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+    mode: Mode,
+    // Position within the current line
+    dot: u16,
+    // The STAT interrupt is only requested on a rising edge of all the enabled
+    // conditions ORed together, so overlapping conditions block each other
+    stat_line: bool,
+    // IF bits requested since the last tick
+    interrupts: u8,
+    framebuffer: [u8; FRAMEBUFFER_SIZE],
+    // Frames completed since power on
+    frames: u64,
+    // The window starts once LY has matched WY during the frame
+    window_triggered: bool,
+    // The window line being drawn, which only advances on lines that show the window
+    window_line: u8,
+    renderer: Renderer,
+    fifo: PixelFifo,
+}
+
+impl Default for PPU {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PPU {
+    pub fn new() -> Self {
+        Self::with_renderer(Renderer::Scanline)
+    }
+
+    pub fn with_renderer(renderer: Renderer) -> Self {
+        PPU {
+            vram: [0; VRAM_SIZE],
+            oam: [0; OAM_SIZE],
+            lcdc: 0,
+            stat: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0,
+            obp0: 0,
+            obp1: 0,
+            wy: 0,
+            wx: 0,
+            mode: Mode::HBlank,
+            dot: 0,
+            stat_line: false,
+            interrupts: 0,
+            framebuffer: [0; FRAMEBUFFER_SIZE],
+            frames: 0,
+            window_triggered: false,
+            window_line: 0,
+            renderer,
+            fifo: PixelFifo::new(),
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn ly(&self) -> u8 {
+        self.ly
+    }
+
+    // The last rendered frame, complete whenever frames() goes up
+    pub fn framebuffer(&self) -> &[u8; FRAMEBUFFER_SIZE] {
+        &self.framebuffer
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    // Advances the PPU by one M-cycle and returns the IF bits it requested
+    pub fn tick(&mut self) -> u8 {
+        if self.lcd_enabled() {
+            for _ in 0..DOTS_PER_M_CYCLE {
+                self.tick_dot();
+            }
+        }
+        std::mem::take(&mut self.interrupts)
+    }
+
+    fn tick_dot(&mut self) {
+        self.dot += 1;
+        if self.dot == DOTS_PER_LINE {
+            self.dot = 0;
+            self.ly = (self.ly + 1) % LINES_PER_FRAME;
+            if self.ly == 0 {
+                self.start_frame();
+            }
+        }
+
+        let mode = match self.mode {
+            _ if self.ly >= SCREEN_HEIGHT => Mode::VBlank,
+            Mode::HBlank | Mode::VBlank if self.dot == 0 => Mode::OAMScan,
+            Mode::OAMScan if self.dot == OAM_SCAN_DOTS => Mode::PixelTransfer,
+            Mode::PixelTransfer => {
+                if self.pixel_transfer_done() {
+                    Mode::HBlank
+                } else {
+                    Mode::PixelTransfer
+                }
+            }
+            mode => mode,
+        };
+        if mode != self.mode {
+            self.mode = mode;
+            match mode {
+                Mode::PixelTransfer if self.renderer == Renderer::Fifo => self.start_pixel_fifo(),
+                Mode::HBlank if self.renderer == Renderer::Scanline => self.render_scanline(),
+                Mode::VBlank => {
+                    self.frames += 1;
+                    self.interrupts |= Interrupt::VBlank.bit();
+                }
+                _ => {}
+            }
+        }
+
+        self.update_stat_line();
+    }
+
+    // Mode 3 always takes 172 dots with the scanline renderer,
+    // the FIFO takes as long as it needs to push 160 pixels
+    fn pixel_transfer_done(&mut self) -> bool {
+        match self.renderer {
+            Renderer::Scanline => self.dot == OAM_SCAN_DOTS + PIXEL_TRANSFER_DOTS,
+            Renderer::Fifo => self.step_pixel_fifo(),
+        }
+    }
+
+    // The window can only start on lines after LY has matched WY in the frame
+    fn check_window_trigger(&mut self) {
+        if self.ly == self.wy {
+            self.window_triggered = true;
+        }
+    }
+
+    fn start_frame(&mut self) {
+        self.window_triggered = false;
+        self.window_line = 0;
+    }
+
+    fn lcd_enabled(&self) -> bool {
+        self.lcdc & LCDC_LCD_ENABLE != 0
+    }
+
+    fn stat_conditions(&self) -> bool {
+        (self.stat & STAT_LYC_INTERRUPT != 0 && self.ly == self.lyc)
+            || match self.mode {
+                Mode::HBlank => self.stat & STAT_HBLANK_INTERRUPT != 0,
+                Mode::VBlank => self.stat & STAT_VBLANK_INTERRUPT != 0,
+                Mode::OAMScan => self.stat & STAT_OAM_SCAN_INTERRUPT != 0,
+                Mode::PixelTransfer => false,
+            }
+    }
+
+    fn update_stat_line(&mut self) {
+        let line = self.stat_conditions();
+        if line && !self.stat_line {
+            self.interrupts |= Interrupt::LCDStat.bit();
+        }
+        self.stat_line = line;
+    }
+
+    pub(crate) fn read_vram(&self, offset: u16) -> u8 {
+        self.vram[offset as usize]
+    }
+
+    pub(crate) fn write_vram(&mut self, offset: u16, byte: u8) {
+        self.vram[offset as usize] = byte;
+    }
+
+    pub(crate) fn read_oam(&self, offset: u16) -> u8 {
+        self.oam[offset as usize]
+    }
+
+    pub(crate) fn write_oam(&mut self, offset: u16, byte: u8) {
+        self.oam[offset as usize] = byte;
+    }
+
+    pub(crate) fn read_register(&self, address: u16) -> u8 {
+        match address {
+            LCDC_ADDRESS => self.lcdc,
+            STAT_ADDRESS => {
+                let lyc_equal = if self.ly == self.lyc {
+                    STAT_LYC_EQUAL
+                } else {
+                    0
+                };
+                STAT_UNUSED | self.stat | lyc_equal | u8::from(self.mode)
+            }
+            SCY_ADDRESS => self.scy,
+            SCX_ADDRESS => self.scx,
+            LY_ADDRESS => self.ly,
+            LYC_ADDRESS => self.lyc,
+            BGP_ADDRESS => self.bgp,
+            OBP0_ADDRESS => self.obp0,
+            OBP1_ADDRESS => self.obp1,
+            WY_ADDRESS => self.wy,
+            WX_ADDRESS => self.wx,
+            _ => panic!("0x{:04x} is not a PPU register", address),
+        }
+    }
+
+    pub(crate) fn write_register(&mut self, address: u16, byte: u8) {
+        match address {
+            LCDC_ADDRESS => {
+                let was_enabled = self.lcd_enabled();
+                self.lcdc = byte;
+                if was_enabled != self.lcd_enabled() {
+                    // The PPU restarts from the top of the frame either way
+                    self.ly = 0;
+                    self.dot = 0;
+                    self.start_frame();
+                    self.mode = if self.lcd_enabled() {
+                        Mode::OAMScan
+                    } else {
+                        Mode::HBlank
+                    };
+                }
+            }
+            STAT_ADDRESS => self.stat = byte & STAT_WRITABLE,
+            SCY_ADDRESS => self.scy = byte,
+            SCX_ADDRESS => self.scx = byte,
+            // LY is read only
+            LY_ADDRESS => {}
+            LYC_ADDRESS => self.lyc = byte,
+            BGP_ADDRESS => self.bgp = byte,
+            OBP0_ADDRESS => self.obp0 = byte,
+            OBP1_ADDRESS => self.obp1 = byte,
+            WY_ADDRESS => self.wy = byte,
+            WX_ADDRESS => self.wx = byte,
+            _ => panic!("0x{:04x} is not a PPU register", address),
+        }
+
+        if self.lcd_enabled() {
+            self.update_stat_line();
+        }
+    }
+
+    // Sets a register to a saved value without the side effects of writing it:
+    // LCDC doesn't restart the PPU, STAT sets the mode and LY can be changed
+    pub(crate) fn load_register(&mut self, address: u16, byte: u8) {
+        match address {
+            LCDC_ADDRESS => self.lcdc = byte,
+            STAT_ADDRESS => {
+                self.stat = byte & STAT_WRITABLE;
+                self.mode = match byte & 0b11 {
+                    0 => Mode::HBlank,
+                    1 => Mode::VBlank,
+                    2 => Mode::OAMScan,
+                    _ => Mode::PixelTransfer,
+                };
+            }
+            LY_ADDRESS => self.ly = byte,
+            _ => self.write_register(address, byte),
+        }
+        self.stat_line = self.stat_conditions();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick_dots(ppu: &mut PPU, dots: u32) -> u8 {
+        let mut interrupts = 0;
+        for _ in 0..dots / DOTS_PER_M_CYCLE as u32 {
+            interrupts |= ppu.tick();
+        }
+        interrupts
+    }
+
+    fn enabled_ppu() -> PPU {
+        let mut ppu = PPU::new();
+        ppu.write_register(LCDC_ADDRESS, LCDC_LCD_ENABLE);
+        ppu
+    }
+
+    #[test]
+    fn mode_timing() {
+        let mut ppu = enabled_ppu();
+        assert_eq!(ppu.mode(), Mode::OAMScan);
+
+        tick_dots(&mut ppu, 80);
+        assert_eq!(ppu.mode(), Mode::PixelTransfer);
+        assert_eq!(ppu.read_register(STAT_ADDRESS) & 0b11, 3);
+
+        tick_dots(&mut ppu, 172);
+        assert_eq!(ppu.mode(), Mode::HBlank);
+
+        tick_dots(&mut ppu, 204);
+        assert_eq!(ppu.mode(), Mode::OAMScan);
+        assert_eq!(ppu.ly(), 1);
+    }
+
+    #[test]
+    fn vblank() {
+        let mut ppu = enabled_ppu();
+
+        assert_eq!(tick_dots(&mut ppu, 456 * 144 - 4), 0);
+        assert_eq!(ppu.ly(), 143);
+        assert_eq!(tick_dots(&mut ppu, 4), Interrupt::VBlank.bit());
+        assert_eq!(ppu.mode(), Mode::VBlank);
+        assert_eq!(ppu.ly(), 144);
+
+        // A frame is 154 lines
+        tick_dots(&mut ppu, 456 * 10);
+        assert_eq!(ppu.ly(), 0);
+        assert_eq!(ppu.mode(), Mode::OAMScan);
+    }
+
+    #[test]
+    fn lyc() {
+        let mut ppu = enabled_ppu();
+        ppu.write_register(LYC_ADDRESS, 2);
+        ppu.write_register(STAT_ADDRESS, STAT_LYC_INTERRUPT);
+        assert_eq!(ppu.read_register(STAT_ADDRESS), 0xC2);
+
+        assert_eq!(tick_dots(&mut ppu, 456 * 2 - 4), 0);
+        assert_eq!(tick_dots(&mut ppu, 4), Interrupt::LCDStat.bit());
+        assert_eq!(ppu.read_register(STAT_ADDRESS), 0xC6);
+
+        // Writing LYC to the current line also triggers it
+        tick_dots(&mut ppu, 456);
+        ppu.write_register(LYC_ADDRESS, 3);
+        assert_eq!(ppu.tick(), Interrupt::LCDStat.bit());
+    }
+
+    #[test]
+    fn stat_blocking() {
+        let mut ppu = enabled_ppu();
+        ppu.write_register(LYC_ADDRESS, 1);
+        ppu.write_register(STAT_ADDRESS, STAT_LYC_INTERRUPT | STAT_HBLANK_INTERRUPT);
+
+        // HBlank of line 0 raises the line...
+        assert_eq!(tick_dots(&mut ppu, 252), Interrupt::LCDStat.bit());
+        // ...which LY == LYC on line 1 keeps high, so there is no second interrupt
+        assert_eq!(tick_dots(&mut ppu, 204), 0);
+        assert_eq!(ppu.ly(), 1);
+
+        // Without HBlank enabled the line drops and LYC gets its own interrupt
+        let mut ppu = enabled_ppu();
+        ppu.write_register(LYC_ADDRESS, 1);
+        ppu.write_register(STAT_ADDRESS, STAT_LYC_INTERRUPT | STAT_OAM_SCAN_INTERRUPT);
+        tick_dots(&mut ppu, 80);
+        assert_eq!(tick_dots(&mut ppu, 376), Interrupt::LCDStat.bit());
+    }
+
+    #[test]
+    fn vblank_stat() {
+        let mut ppu = enabled_ppu();
+        ppu.write_register(STAT_ADDRESS, STAT_VBLANK_INTERRUPT);
+        assert_eq!(
+            tick_dots(&mut ppu, 456 * 144),
+            Interrupt::VBlank.bit() | Interrupt::LCDStat.bit()
+        );
+    }
+
+    #[test]
+    fn lcd_off() {
+        let mut ppu = enabled_ppu();
+        tick_dots(&mut ppu, 456 * 3 + 100);
+        assert_eq!(ppu.ly(), 3);
+
+        ppu.write_register(LCDC_ADDRESS, 0x00);
+        assert_eq!(ppu.ly(), 0);
+        assert_eq!(ppu.read_register(STAT_ADDRESS) & 0b11, 0);
+        tick_dots(&mut ppu, 456 * 3);
+        assert_eq!(ppu.ly(), 0);
+
+        // LY is read only
+        ppu.write_register(LY_ADDRESS, 0x10);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 0x00);
+    }
+}
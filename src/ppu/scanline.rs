@@ -0,0 +1,329 @@
+use super::sprites::{ObjPixel, Sprite};
+use super::{
+    LCDC_BG_TILE_MAP, LCDC_BG_WINDOW_ENABLE, LCDC_OBJ_ENABLE, LCDC_TILE_DATA, LCDC_WINDOW_ENABLE,
+    LCDC_WINDOW_TILE_MAP, PPU, SCREEN_WIDTH, SIGNED_TILE_DATA, TILE_MAP_0, TILE_MAP_1,
+    TILE_MAP_WIDTH, TILE_SIZE,
+};
+
+// The window is drawn from screen X = WX - 7
+pub(super) const WINDOW_X_OFFSET: u8 = 7;
+
+impl PPU {
+    // Draws the whole of line LY at once at the end of pixel transfer
+    pub(super) fn render_scanline(&mut self) {
+        self.check_window_trigger();
+        let window_visible = self.lcdc & LCDC_WINDOW_ENABLE != 0
+            && self.window_triggered
+            && self.wx < SCREEN_WIDTH + WINDOW_X_OFFSET;
+
+        // On DMG the sprite with the smallest X wins, then the one first in OAM
+        let mut sprites = if self.lcdc & LCDC_OBJ_ENABLE != 0 {
+            self.scan_oam()
+        } else {
+            Vec::new()
+        };
+        sprites.sort_by_key(|sprite| sprite.x);
+
+        let line_start = self.ly as usize * SCREEN_WIDTH as usize;
+        for x in 0..SCREEN_WIDTH {
+            let bg_color = if self.lcdc & LCDC_BG_WINDOW_ENABLE == 0 {
+                // On DMG this blanks both the background and the window
+                0
+            } else if window_visible && x + WINDOW_X_OFFSET >= self.wx {
+                self.tile_map_color(
+                    LCDC_WINDOW_TILE_MAP,
+                    x + WINDOW_X_OFFSET - self.wx,
+                    self.window_line,
+                )
+            } else {
+                self.tile_map_color(
+                    LCDC_BG_TILE_MAP,
+                    x.wrapping_add(self.scx),
+                    self.ly.wrapping_add(self.scy),
+                )
+            };
+            self.framebuffer[line_start + x as usize] = self.mix_sprites(&sprites, x, bg_color);
+        }
+
+        if window_visible {
+            self.window_line += 1;
+        }
+    }
+
+    // The final shade at screen column x given the background color index under it
+    fn mix_sprites(&self, sprites: &[Sprite], x: u8, bg_color: u8) -> u8 {
+        let obj = sprites
+            .iter()
+            .filter(|sprite| sprite.covers(x))
+            .map(|sprite| ObjPixel::new(sprite, self.sprite_color(sprite, x)))
+            .find(|pixel| pixel.color != 0);
+        self.mix(bg_color, obj)
+    }
+
+    // Picks between the background and the highest priority opaque sprite pixel
+    pub(super) fn mix(&self, bg_color: u8, obj: Option<ObjPixel>) -> u8 {
+        match obj {
+            Some(pixel) if pixel.behind_background && bg_color != 0 => shade(self.bgp, bg_color),
+            Some(pixel) => {
+                let palette = if pixel.obp1 { self.obp1 } else { self.obp0 };
+                shade(palette, pixel.color)
+            }
+            None => shade(self.bgp, bg_color),
+        }
+    }
+
+    // The color index (0-3) at x, y of the 256x256 map selected by map_bit of LCDC
+    fn tile_map_color(&self, map_bit: u8, x: u8, y: u8) -> u8 {
+        let map = if self.lcdc & map_bit != 0 {
+            TILE_MAP_1
+        } else {
+            TILE_MAP_0
+        };
+        let index = map + (y as usize / 8) * TILE_MAP_WIDTH + x as usize / 8;
+        let tile = self.bg_tile_address(self.vram[index]);
+        self.tile_color(tile, x % 8, y % 8)
+    }
+
+    pub(super) fn bg_tile_address(&self, tile: u8) -> usize {
+        if self.lcdc & LCDC_TILE_DATA != 0 {
+            tile as usize * TILE_SIZE
+        } else {
+            (SIGNED_TILE_DATA as isize + tile as i8 as isize * TILE_SIZE as isize) as usize
+        }
+    }
+
+    // Each row of a tile is two bytes, low bits first, with the leftmost pixel in bit 7
+    pub(super) fn tile_color(&self, tile_address: usize, x: u8, y: u8) -> u8 {
+        let row = tile_address + y as usize * 2;
+        let bit = 7 - x;
+        let low = (self.vram[row] >> bit) & 1;
+        let high = (self.vram[row + 1] >> bit) & 1;
+        high << 1 | low
+    }
+}
+
+// Maps a color index through a BGP/OBP palette
+pub(super) fn shade(palette: u8, color: u8) -> u8 {
+    (palette >> (color * 2)) & 0b11
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::sprites::tests::write_sprite;
+    use super::super::sprites::{ATTRIBUTE_BG_PRIORITY, ATTRIBUTE_PALETTE};
+    use super::super::{BGP_ADDRESS, LCDC_ADDRESS, LCDC_LCD_ENABLE, LCDC_OBJ_SIZE};
+    use super::*;
+
+    // The identity palette so shades equal color indices
+    const IDENTITY_PALETTE: u8 = 0b11_10_01_00;
+
+    fn ppu(lcdc: u8) -> PPU {
+        let mut ppu = PPU::new();
+        ppu.write_register(LCDC_ADDRESS, LCDC_LCD_ENABLE | LCDC_BG_WINDOW_ENABLE | lcdc);
+        ppu.write_register(BGP_ADDRESS, IDENTITY_PALETTE);
+        ppu
+    }
+
+    // A tile whose pixel colors go 0, 1, 2, 3, 0, 1, 2, 3 on every row
+    fn write_stripes(ppu: &mut PPU, tile_address: usize) {
+        for row in 0..8 {
+            ppu.vram[tile_address + row * 2] = 0b0101_0101;
+            ppu.vram[tile_address + row * 2 + 1] = 0b0011_0011;
+        }
+    }
+
+    fn solid_tile(ppu: &mut PPU, tile_address: usize, color: u8) {
+        for row in 0..8 {
+            ppu.vram[tile_address + row * 2] = if color & 1 != 0 { 0xFF } else { 0x00 };
+            ppu.vram[tile_address + row * 2 + 1] = if color & 2 != 0 { 0xFF } else { 0x00 };
+        }
+    }
+
+    fn render_line(ppu: &mut PPU, ly: u8) -> Vec<u8> {
+        ppu.ly = ly;
+        ppu.render_scanline();
+        let start = ly as usize * SCREEN_WIDTH as usize;
+        ppu.framebuffer[start..start + SCREEN_WIDTH as usize].to_vec()
+    }
+
+    #[test]
+    fn tile_color() {
+        let mut ppu = PPU::new();
+        write_stripes(&mut ppu, 0x0010);
+        let row: Vec<u8> = (0..8).map(|x| ppu.tile_color(0x0010, x, 3)).collect();
+        assert_eq!(row, [0, 1, 2, 3, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn shade() {
+        assert_eq!(super::shade(0b11_10_01_00, 2), 2);
+        assert_eq!(super::shade(0b00_01_10_11, 0), 3);
+    }
+
+    #[test]
+    fn background() {
+        let mut ppu = ppu(LCDC_TILE_DATA);
+        write_stripes(&mut ppu, 0x0010);
+        ppu.vram[TILE_MAP_0] = 1;
+
+        let line = render_line(&mut ppu, 0);
+        assert_eq!(line[..10], [0, 1, 2, 3, 0, 1, 2, 3, 0, 0]);
+
+        // BGP maps the colors to shades
+        ppu.write_register(BGP_ADDRESS, 0b00_00_00_11);
+        let line = render_line(&mut ppu, 0);
+        assert_eq!(line[..4], [3, 0, 0, 0]);
+    }
+
+    #[test]
+    fn scroll() {
+        let mut ppu = ppu(LCDC_TILE_DATA);
+        solid_tile(&mut ppu, 0x0010, 3);
+        // The bottom right tile of the map
+        ppu.vram[TILE_MAP_0 + 31 * TILE_MAP_WIDTH + 31] = 1;
+        ppu.scx = 252;
+        ppu.scy = 250;
+
+        let line = render_line(&mut ppu, 1);
+        assert_eq!(line[..5], [3, 3, 3, 3, 0]);
+        // The map wraps around vertically too
+        let line = render_line(&mut ppu, 6);
+        assert_eq!(line[0], 0);
+    }
+
+    #[test]
+    fn signed_addressing() {
+        let mut ppu = ppu(LCDC_BG_TILE_MAP);
+        solid_tile(&mut ppu, 0x0FF0, 1);
+        solid_tile(&mut ppu, 0x1000, 2);
+        ppu.vram[TILE_MAP_1] = 0xFF;
+        ppu.vram[TILE_MAP_1 + 1] = 0x00;
+
+        let line = render_line(&mut ppu, 0);
+        assert_eq!(line[0], 1);
+        assert_eq!(line[8], 2);
+    }
+
+    #[test]
+    fn background_disabled() {
+        let mut ppu = ppu(LCDC_TILE_DATA);
+        solid_tile(&mut ppu, 0x0000, 3);
+        ppu.lcdc &= !LCDC_BG_WINDOW_ENABLE;
+        assert!(render_line(&mut ppu, 0).iter().all(|&shade| shade == 0));
+    }
+
+    #[test]
+    fn window() {
+        let mut ppu = ppu(LCDC_TILE_DATA | LCDC_WINDOW_ENABLE | LCDC_WINDOW_TILE_MAP);
+        solid_tile(&mut ppu, 0x0010, 3);
+        solid_tile(&mut ppu, 0x0020, 2);
+        // The first two rows of the window map
+        ppu.vram[TILE_MAP_1] = 1;
+        ppu.vram[TILE_MAP_1 + TILE_MAP_WIDTH] = 2;
+        ppu.wx = 7 + 80;
+        ppu.wy = 2;
+
+        assert_eq!(render_line(&mut ppu, 1)[80], 0);
+        let line = render_line(&mut ppu, 2);
+        assert_eq!(line[79], 0);
+        assert_eq!(line[80], 3);
+        assert_eq!(line[88], 0);
+
+        // Lines with the window off don't advance its line counter
+        ppu.lcdc &= !LCDC_WINDOW_ENABLE;
+        for ly in 3..20 {
+            render_line(&mut ppu, ly);
+        }
+        ppu.lcdc |= LCDC_WINDOW_ENABLE;
+        for ly in 20..27 {
+            assert_eq!(render_line(&mut ppu, ly)[80], 3, "LY {}", ly);
+        }
+        assert_eq!(render_line(&mut ppu, 27)[80], 2);
+
+        // A WX past the screen hides the window
+        ppu.wx = 167;
+        assert_eq!(render_line(&mut ppu, 28)[159], 0);
+    }
+
+    #[test]
+    fn sprites() {
+        let mut ppu = ppu(LCDC_TILE_DATA | LCDC_OBJ_ENABLE);
+        ppu.obp0 = IDENTITY_PALETTE;
+        ppu.obp1 = 0b00_00_00_00;
+        write_stripes(&mut ppu, 0x0010);
+        write_sprite(&mut ppu, 0, 16, 8 + 20, 1, 0);
+        write_sprite(&mut ppu, 1, 16, 8 + 40, 1, ATTRIBUTE_PALETTE);
+
+        let line = render_line(&mut ppu, 0);
+        assert_eq!(line[20..24], [0, 1, 2, 3]);
+        // OBP1 turns every color white
+        assert_eq!(line[41..44], [0, 0, 0]);
+
+        // Disabled sprites aren't drawn
+        ppu.lcdc &= !LCDC_OBJ_ENABLE;
+        assert_eq!(render_line(&mut ppu, 0)[21], 0);
+    }
+
+    #[test]
+    fn sprite_x_priority() {
+        let mut ppu = ppu(LCDC_TILE_DATA | LCDC_OBJ_ENABLE);
+        ppu.obp0 = IDENTITY_PALETTE;
+        solid_tile(&mut ppu, 0x0010, 1);
+        solid_tile(&mut ppu, 0x0020, 2);
+        write_stripes(&mut ppu, 0x0030);
+
+        // The smaller X wins even though it comes later in OAM
+        write_sprite(&mut ppu, 0, 16, 8 + 4, 1, 0);
+        write_sprite(&mut ppu, 1, 16, 8 + 2, 2, 0);
+        // With equal X the first in OAM wins
+        write_sprite(&mut ppu, 2, 16, 8 + 40, 1, 0);
+        write_sprite(&mut ppu, 3, 16, 8 + 40, 2, 0);
+        // Transparent pixels show the sprite behind
+        write_sprite(&mut ppu, 4, 16, 8 + 60, 3, 0);
+        write_sprite(&mut ppu, 5, 16, 8 + 60, 2, 0);
+
+        let line = render_line(&mut ppu, 0);
+        assert_eq!(line[4], 2);
+        assert_eq!(line[10], 1);
+        assert_eq!(line[40], 1);
+        assert_eq!(line[60..62], [2, 1]);
+    }
+
+    #[test]
+    fn sprite_bg_priority() {
+        let mut ppu = ppu(LCDC_TILE_DATA | LCDC_OBJ_ENABLE);
+        ppu.obp0 = IDENTITY_PALETTE;
+        write_stripes(&mut ppu, 0x0000);
+        solid_tile(&mut ppu, 0x0010, 3);
+        write_sprite(&mut ppu, 0, 16, 8, 1, ATTRIBUTE_BG_PRIORITY);
+
+        // Only background color 0 lets the sprite through
+        let line = render_line(&mut ppu, 0);
+        assert_eq!(line[..4], [3, 1, 2, 3]);
+    }
+
+    #[test]
+    fn tall_sprites() {
+        let mut ppu = ppu(LCDC_TILE_DATA | LCDC_OBJ_ENABLE | LCDC_OBJ_SIZE);
+        ppu.obp0 = IDENTITY_PALETTE;
+        solid_tile(&mut ppu, 0x0020, 1);
+        solid_tile(&mut ppu, 0x0030, 2);
+        write_sprite(&mut ppu, 0, 16, 8, 3, 0);
+
+        assert_eq!(render_line(&mut ppu, 0)[0], 1);
+        assert_eq!(render_line(&mut ppu, 15)[0], 2);
+        assert_eq!(render_line(&mut ppu, 16)[0], 0);
+    }
+
+    #[test]
+    fn frame() {
+        let mut ppu = ppu(LCDC_TILE_DATA);
+        solid_tile(&mut ppu, 0x0000, 2);
+
+        for _ in 0..114 * 154 {
+            ppu.tick();
+        }
+        assert_eq!(ppu.frames(), 1);
+        assert!(ppu.framebuffer().iter().all(|&shade| shade == 2));
+    }
+}
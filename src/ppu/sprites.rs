@@ -0,0 +1,208 @@
+use super::{LCDC_OBJ_SIZE, PPU, TILE_SIZE};
+
+pub const MAX_SPRITES_PER_LINE: usize = 10;
+const SPRITE_COUNT: usize = 40;
+const SPRITE_BYTES: usize = 4;
+
+// OAM positions are offset so sprites can be partially off the top and left of the screen
+const SPRITE_Y_OFFSET: u8 = 16;
+const SPRITE_X_OFFSET: u8 = 8;
+
+pub(super) const ATTRIBUTE_PALETTE: u8 = 1 << 4;
+const ATTRIBUTE_X_FLIP: u8 = 1 << 5;
+const ATTRIBUTE_Y_FLIP: u8 = 1 << 6;
+// The sprite is only drawn over background color 0
+pub(super) const ATTRIBUTE_BG_PRIORITY: u8 = 1 << 7;
+
+// One OAM entry
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sprite {
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub attributes: u8,
+    pub oam_index: u8,
+}
+
+impl Sprite {
+    pub fn uses_obp1(&self) -> bool {
+        self.attributes & ATTRIBUTE_PALETTE != 0
+    }
+
+    pub fn behind_background(&self) -> bool {
+        self.attributes & ATTRIBUTE_BG_PRIORITY != 0
+    }
+
+    // Whether screen column x falls within the sprite
+    pub fn covers(&self, x: u8) -> bool {
+        let x = x as u16 + SPRITE_X_OFFSET as u16;
+        x >= self.x as u16 && x < self.x as u16 + 8
+    }
+}
+
+// A sprite pixel waiting to be mixed with the background
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ObjPixel {
+    // 0 is transparent
+    pub color: u8,
+    pub obp1: bool,
+    pub behind_background: bool,
+}
+
+impl ObjPixel {
+    pub const TRANSPARENT: ObjPixel = ObjPixel {
+        color: 0,
+        obp1: false,
+        behind_background: false,
+    };
+
+    pub fn new(sprite: &Sprite, color: u8) -> Self {
+        ObjPixel {
+            color,
+            obp1: sprite.uses_obp1(),
+            behind_background: sprite.behind_background(),
+        }
+    }
+}
+
+impl PPU {
+    pub(super) fn sprite_height(&self) -> u8 {
+        if self.lcdc & LCDC_OBJ_SIZE != 0 {
+            16
+        } else {
+            8
+        }
+    }
+
+    // The first 10 sprites in OAM order that are on line LY. Off screen X
+    // positions still count towards the limit.
+    pub(super) fn scan_oam(&self) -> Vec<Sprite> {
+        let line = self.ly as u16 + SPRITE_Y_OFFSET as u16;
+        let height = self.sprite_height() as u16;
+        self.oam
+            .chunks(SPRITE_BYTES)
+            .take(SPRITE_COUNT)
+            .enumerate()
+            .map(|(i, entry)| Sprite {
+                y: entry[0],
+                x: entry[1],
+                tile: entry[2],
+                attributes: entry[3],
+                oam_index: i as u8,
+            })
+            .filter(|sprite| line >= sprite.y as u16 && line < sprite.y as u16 + height)
+            .take(MAX_SPRITES_PER_LINE)
+            .collect()
+    }
+
+    // The color index of sprite at screen column x on line LY, where 0 is transparent
+    pub(super) fn sprite_color(&self, sprite: &Sprite, x: u8) -> u8 {
+        let height = self.sprite_height();
+        let mut row = self.ly + SPRITE_Y_OFFSET - sprite.y;
+        if sprite.attributes & ATTRIBUTE_Y_FLIP != 0 {
+            row = height - 1 - row;
+        }
+        let mut column = x + SPRITE_X_OFFSET - sprite.x;
+        if sprite.attributes & ATTRIBUTE_X_FLIP != 0 {
+            column = 7 - column;
+        }
+
+        // 8x16 sprites ignore the lowest bit of the tile index
+        let tile = if height == 16 {
+            (sprite.tile & 0xFE) + row / 8
+        } else {
+            sprite.tile
+        };
+        self.tile_color(tile as usize * TILE_SIZE, column, row % 8)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub fn write_sprite(ppu: &mut PPU, index: usize, y: u8, x: u8, tile: u8, attributes: u8) {
+        let entry = index * SPRITE_BYTES;
+        ppu.oam[entry..entry + SPRITE_BYTES].copy_from_slice(&[y, x, tile, attributes]);
+    }
+
+    #[test]
+    fn scan_oam() {
+        let mut ppu = PPU::new();
+        write_sprite(&mut ppu, 0, 16, 8, 0, 0);
+        write_sprite(&mut ppu, 1, 17, 8, 0, 0);
+        write_sprite(&mut ppu, 2, 8, 8, 0, 0);
+
+        ppu.ly = 0;
+        let sprites: Vec<u8> = ppu.scan_oam().iter().map(|s| s.oam_index).collect();
+        assert_eq!(sprites, [0]);
+
+        ppu.ly = 7;
+        let sprites: Vec<u8> = ppu.scan_oam().iter().map(|s| s.oam_index).collect();
+        assert_eq!(sprites, [0, 1]);
+
+        // Sprite 2 only reaches line 7 when it is 16 pixels tall
+        ppu.lcdc |= LCDC_OBJ_SIZE;
+        ppu.ly = 7;
+        let sprites: Vec<u8> = ppu.scan_oam().iter().map(|s| s.oam_index).collect();
+        assert_eq!(sprites, [0, 1, 2]);
+    }
+
+    #[test]
+    fn ten_per_line() {
+        let mut ppu = PPU::new();
+        for i in 0..SPRITE_COUNT {
+            // X = 0 is off screen but still selected
+            write_sprite(&mut ppu, i, 16, 0, 0, 0);
+        }
+        let sprites = ppu.scan_oam();
+        assert_eq!(sprites.len(), MAX_SPRITES_PER_LINE);
+        assert_eq!(sprites[9].oam_index, 9);
+    }
+
+    #[test]
+    fn sprite_color() {
+        let mut ppu = PPU::new();
+        // Tile 2 has color 1 in the top left pixel, tile 3 color 2 in its bottom right
+        ppu.vram[2 * TILE_SIZE] = 0x80;
+        ppu.vram[3 * TILE_SIZE + 15] = 0x01;
+        let sprite = |tile, attributes| Sprite {
+            y: 16,
+            x: 8,
+            tile,
+            attributes,
+            oam_index: 0,
+        };
+
+        ppu.ly = 0;
+        assert_eq!(ppu.sprite_color(&sprite(2, 0), 0), 1);
+        assert_eq!(ppu.sprite_color(&sprite(2, ATTRIBUTE_X_FLIP), 7), 1);
+        ppu.ly = 7;
+        assert_eq!(ppu.sprite_color(&sprite(2, ATTRIBUTE_Y_FLIP), 0), 1);
+        assert_eq!(ppu.sprite_color(&sprite(3, 0), 7), 2);
+
+        // In 8x16 mode tiles 2 and 3 make up one sprite, whatever the low bit
+        ppu.lcdc |= LCDC_OBJ_SIZE;
+        ppu.ly = 15;
+        assert_eq!(ppu.sprite_color(&sprite(3, 0), 7), 2);
+        ppu.ly = 0;
+        assert_eq!(
+            ppu.sprite_color(&sprite(3, ATTRIBUTE_Y_FLIP | ATTRIBUTE_X_FLIP), 0),
+            2
+        );
+    }
+
+    #[test]
+    fn covers() {
+        let sprite = Sprite {
+            y: 16,
+            x: 4,
+            tile: 0,
+            attributes: 0,
+            oam_index: 0,
+        };
+        assert!(sprite.covers(0));
+        assert!(sprite.covers(3));
+        assert!(!sprite.covers(4));
+    }
+}
@@ -0,0 +1,124 @@
+// Runs the mooneye-test-suite timer ROMs. The ROMs aren't distributed with the
+// crate, so these are ignored by default: point MOONEYE_ROMS at an extracted
+// suite (the directory containing acceptance/) or put it in tests/roms/mooneye
+// and run `cargo test --test mooneye -- --ignored`. The timer is only ticked
+// after each instruction, so the ROMs that depend on the exact M-cycle of a
+// DIV or TAC write are expected to fail.
+use std::env;
+use std::path::PathBuf;
+
+use gameboy_emu_rs::cartridge::Cartridge;
+use gameboy_emu_rs::cpu::{Model, CPU};
+
+// The tests finish well within 20 emulated seconds
+const TIMEOUT_T_CYCLES: u64 = 20 * 4_194_304;
+
+// Mooneye tests load the Fibonacci numbers into B, C, D, E, H and L on success
+const PASS_REGISTERS: (u16, u16, u16) = (0x0305, 0x080D, 0x1522);
+// and 0x42 into all of them on failure
+const FAIL_REGISTERS: (u16, u16, u16) = (0x4242, 0x4242, 0x4242);
+
+fn rom_dir() -> PathBuf {
+    match env::var_os("MOONEYE_ROMS") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms/mooneye"),
+    }
+}
+
+fn run(name: &str) {
+    let path = rom_dir().join(name);
+    let cartridge = Cartridge::from_file(&path)
+        .unwrap_or_else(|error| panic!("couldn't load {}: {}", path.display(), error));
+    let mut cpu = CPU::new();
+    cpu.insert_cartridge(cartridge);
+    cpu.skip_boot(Model::DMG);
+
+    while cpu.cycles() < TIMEOUT_T_CYCLES {
+        cpu.step().unwrap();
+        let registers = cpu.registers_snapshot();
+        let result = (registers.bc, registers.de, registers.hl);
+        if result == PASS_REGISTERS {
+            return;
+        }
+        assert_ne!(result, FAIL_REGISTERS, "{} failed", name);
+    }
+    panic!("{} timed out", name);
+}
+
+#[test]
+#[ignore]
+fn div_write() {
+    run("acceptance/timer/div_write.gb");
+}
+
+#[test]
+#[ignore]
+fn rapid_toggle() {
+    run("acceptance/timer/rapid_toggle.gb");
+}
+
+#[test]
+#[ignore]
+fn tim00() {
+    run("acceptance/timer/tim00.gb");
+}
+
+#[test]
+#[ignore]
+fn tim00_div_trigger() {
+    run("acceptance/timer/tim00_div_trigger.gb");
+}
+
+#[test]
+#[ignore]
+fn tim01() {
+    run("acceptance/timer/tim01.gb");
+}
+
+#[test]
+#[ignore]
+fn tim01_div_trigger() {
+    run("acceptance/timer/tim01_div_trigger.gb");
+}
+
+#[test]
+#[ignore]
+fn tim10() {
+    run("acceptance/timer/tim10.gb");
+}
+
+#[test]
+#[ignore]
+fn tim10_div_trigger() {
+    run("acceptance/timer/tim10_div_trigger.gb");
+}
+
+#[test]
+#[ignore]
+fn tim11() {
+    run("acceptance/timer/tim11.gb");
+}
+
+#[test]
+#[ignore]
+fn tim11_div_trigger() {
+    run("acceptance/timer/tim11_div_trigger.gb");
+}
+
+#[test]
+#[ignore]
+fn tima_reload() {
+    run("acceptance/timer/tima_reload.gb");
+}
+
+#[test]
+#[ignore]
+fn tima_write_reloading() {
+    run("acceptance/timer/tima_write_reloading.gb");
+}
+
+#[test]
+#[ignore]
+fn tma_write_reloading() {
+    run("acceptance/timer/tma_write_reloading.gb");
+}